@@ -1,5 +1,8 @@
 #!/bin/env python3
 
+import numpy
+import os
+
 from netCDF4 import Dataset
 
 data = Dataset("empty.nc", "w", format="NETCDF3_CLASSIC")
@@ -21,3 +24,52 @@ temps[:] = [30, 32, 34, 36, 40]
 data.close()
 
 
+data = Dataset("record1.nc", "w", format="NETCDF3_CLASSIC")
+time = data.createDimension("time", None)
+lat = data.createDimension("lat", 2)
+lats = data.createVariable("lat","f4",("lat",))
+lats[:] = [10.5, 20.5]
+times = data.createVariable("times","i2",("time",))
+temps = data.createVariable("temps","f4",("time","lat",))
+times[:] = [1, 2, 3]
+temps[:] = [[280.0, 281.5], [282.0, 283.5], [284.0, 285.5]]
+data.close()
+
+data = Dataset("record2.nc", "w", format="NETCDF3_CLASSIC")
+time = data.createDimension("time", None)
+times = data.createVariable("times","i2",("time",))
+times[:] = [1, 2, 3]
+data.close()
+
+data = Dataset("cdf5.nc", "w", format="NETCDF3_64BIT_DATA")
+data.title = "cdf5"
+time = data.createDimension("time", None)
+x = data.createDimension("x", 3)
+ub = data.createVariable("ub","u1",("x",))
+ub.valid_max = numpy.uint8(250)
+ub[:] = [1, 2, 255]
+us = data.createVariable("us","u2",("x",))
+us[:] = [1, 2, 65535]
+ui = data.createVariable("ui","u4",("x",))
+ui[:] = [1, 2, 4294967295]
+i64 = data.createVariable("i64","i8",("x",))
+i64[:] = [-1, 2**40, 3]
+u64 = data.createVariable("u64","u8",("x",))
+u64[:] = [2**63 + 5, 0, 1]
+rec = data.createVariable("rec","i8",("time",))
+rec[:] = [10, 20]
+data.close()
+
+# Same content as record1.nc in version 2, goes to tests/version2/record1.nc
+os.makedirs("version2", exist_ok=True)
+data = Dataset("version2/record1.nc", "w", format="NETCDF3_64BIT_OFFSET")
+time = data.createDimension("time", None)
+lat = data.createDimension("lat", 2)
+lats = data.createVariable("lat","f4",("lat",))
+lats[:] = [10.5, 20.5]
+times = data.createVariable("times","i2",("time",))
+temps = data.createVariable("temps","f4",("time","lat",))
+times[:] = [1, 2, 3]
+temps[:] = [[280.0, 281.5], [282.0, 283.5], [284.0, 285.5]]
+data.close()
+
@@ -0,0 +1,249 @@
+// Command line tool to inspect and create netCDF files, similar to ncdump and ncgen.
+
+// Rust modules
+use std::env;
+use std::io::{self, Write, BufWriter};
+use std::path::Path;
+use std::process;
+
+// Local modules
+use netcdfrs::prelude::*;
+
+const USAGE: &str = "\
+Usage: netcdfrs <command> [options] <file>...
+
+Commands:
+  dump [-h] [-v var1,...] <file>    print the file as CDL, like ncdump
+                                    -h: header only, -v: data of the given variables only
+  kind <file>                       print the format of the file, like ncdump -k
+  json <file>                       print a JSON summary of the header
+  gen [-k kind] [-o out.nc] <file>  create a netCDF file from CDL, like ncgen
+                                    kind: classic (default), 64-bit offset or cdf5
+  convert -k kind <input> <output>  rewrite a file in another classic format, like nccopy -k
+  help                              print this help
+";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    if let Err(message) = run(&args) {
+        eprintln!("netcdfrs: {}", message);
+        process::exit(1);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+
+    let result = match args.first().map(|command| command.as_str()) {
+        Some("dump") => dump(&mut output, &args[1..]),
+        Some("kind") => kind(&mut output, &args[1..]),
+        Some("json") => json(&mut output, &args[1..]),
+        Some("gen") => gen(&args[1..]),
+        Some("convert") => convert(&args[1..]),
+        Some("help") | Some("-h") | Some("--help") => write!(output, "{}", USAGE).map_err(|error| error.to_string()),
+        Some(command) => Err(format!("unknown command '{}'\n\n{}", command, USAGE)),
+        None => Err(USAGE.to_string()),
+    };
+
+    result?;
+    output.flush().map_err(|error| error.to_string())
+}
+
+/// Splits the arguments into options with a value, flags and the file names.
+struct Arguments {
+    options: Vec<(String, String)>,
+    flags: Vec<String>,
+    files: Vec<String>,
+}
+
+impl Arguments {
+    fn parse(args: &[String], with_value: &[&str], flags: &[&str]) -> Result<Arguments, String> {
+        Arguments::parse_files(args, with_value, flags, 1)
+    }
+
+    /// Like parse(), with the given number of file names.
+    fn parse_files(args: &[String], with_value: &[&str], flags: &[&str], num_of_files: usize) -> Result<Arguments, String> {
+        let mut result = Arguments{options: Vec::new(), flags: Vec::new(), files: Vec::new()};
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            if with_value.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| format!("option {} needs a value", arg))?;
+                result.options.push((arg.clone(), value.clone()));
+            } else if flags.contains(&arg.as_str()) {
+                result.flags.push(arg.clone());
+            } else if arg.starts_with('-') || result.files.len() == num_of_files {
+                return Err(format!("unexpected argument '{}'\n\n{}", arg, USAGE))
+            } else {
+                result.files.push(arg.clone());
+            }
+        }
+
+        if result.files.len() < num_of_files {
+            return Err(format!("missing file name\n\n{}", USAGE))
+        }
+
+        Ok(result)
+    }
+
+    fn file(&self) -> &str {
+        &self.files[0]
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.iter().find(|(option, _)| option == name).map(|(_, value)| value.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+}
+
+/// Like ncdump the name of the data set is the file name without extension.
+fn data_set_name(file: &str) -> String {
+    Path::new(file).file_stem().map_or_else(|| file.to_string(), |stem| stem.to_string_lossy().into_owned())
+}
+
+fn dump<W: Write>(output: &mut W, args: &[String]) -> Result<(), String> {
+    let args = Arguments::parse(args, &["-v"], &["-h"])?;
+    let name = data_set_name(args.file());
+
+    let options = NetCDFCdlOptions{
+        header_only: args.flag("-h"),
+        variables: args.option("-v").map(|variables| variables.split(',').map(|variable| variable.trim().to_string()).collect()),
+    };
+
+    // Only the header and the data of the selected variables are read from the file
+    let mut reader = open_file(args.file()).map_err(|error| error.to_string())?;
+    write_cdl_reader(output, &name, &mut reader, &options).map_err(|error| error.to_string())
+}
+
+/// The same names as "ncdump -k".
+fn kind_name(version: NetCDFVersion) -> &'static str {
+    match version {
+        NetCDFVersion::CDF01 => "classic",
+        NetCDFVersion::CDF02 => "64-bit offset",
+        NetCDFVersion::CDF05 => "cdf5",
+        NetCDFVersion::HDF5 => "netCDF-4",
+    }
+}
+
+fn kind<W: Write>(output: &mut W, args: &[String]) -> Result<(), String> {
+    let args = Arguments::parse(args, &[], &[])?;
+    let version = file_version(args.file()).map_err(|error| error.to_string())?;
+    writeln!(output, "{}", kind_name(version)).map_err(|error| error.to_string())
+}
+
+/// Accepts the kind names and numbers of ncgen -k.
+fn parse_kind(kind: &str) -> Result<NetCDFVersion, String> {
+    match kind {
+        "classic" | "1" | "nc3" => Ok(NetCDFVersion::CDF01),
+        "64-bit offset" | "64-bit-offset" | "2" | "nc6" => Ok(NetCDFVersion::CDF02),
+        "cdf5" | "64-bit data" | "64-bit-data" | "5" | "nc5" => Ok(NetCDFVersion::CDF05),
+        _ => Err(format!("unknown kind '{}', use classic, 64-bit offset or cdf5", kind)),
+    }
+}
+
+fn gen(args: &[String]) -> Result<(), String> {
+    let args = Arguments::parse(args, &["-k", "-o"], &[])?;
+    let version = parse_kind(args.option("-k").unwrap_or("classic"))?;
+    let output = args.option("-o").map_or_else(|| format!("{}.nc", data_set_name(args.file())), |output| output.to_string());
+
+    let netcdf = load_cdl_file(args.file(), version).map_err(|error| error.to_string())?;
+    save_file(&output, &netcdf).map_err(|error| error.to_string())
+}
+
+fn convert(args: &[String]) -> Result<(), String> {
+    let args = Arguments::parse_files(args, &["-k"], &[], 2)?;
+    let version = parse_kind(args.option("-k").ok_or_else(|| format!("option -k is missing\n\n{}", USAGE))?)?;
+    convert_file(&args.files[0], &args.files[1], version).map_err(|error| error.to_string())
+}
+
+fn json<W: Write>(output: &mut W, args: &[String]) -> Result<(), String> {
+    let args = Arguments::parse(args, &[], &[])?;
+    let reader = open_file(args.file()).map_err(|error| error.to_string())?;
+    let header = reader.header();
+
+    let dimensions: Vec<String> = header.list_of_dimensions().iter().map(|dimension| {
+        let unlimited = header.unlimited_dimension().is_some_and(|other| other.name == dimension.name);
+        let length = if unlimited { header.num_of_records() } else { dimension.length };
+
+        format!("{{\"name\": {}, \"length\": {}, \"unlimited\": {}}}", json_string(&dimension.name), length, unlimited)
+    }).collect();
+
+    let variables: Vec<String> = header.list_of_variables().iter().map(|variable| {
+        let dimensions: Vec<String> = header.variable_dimensions(variable).iter()
+            .map(|dimension| json_string(&dimension.name))
+            .collect();
+        let shape: Vec<String> = header.shape(variable).iter().map(|length| length.to_string()).collect();
+
+        format!("{{\"name\": {}, \"type\": \"{}\", \"dimensions\": [{}], \"shape\": [{}], \"attributes\": {}}}",
+            json_string(&variable.name), variable.nc_type.name(), dimensions.join(", "), shape.join(", "),
+            json_attributes(&variable.att_list))
+    }).collect();
+
+    writeln!(output, "{{\n  \"kind\": \"{}\",\n  \"num_of_records\": {},\n  \"dimensions\": [{}],\n  \"attributes\": {},\n  \"variables\": [\n    {}\n  ]\n}}",
+        kind_name(header.version()), header.num_of_records(), dimensions.join(", "),
+        json_attributes(header.list_of_attributes()), variables.join(",\n    "))
+        .map_err(|error| error.to_string())
+}
+
+/// An object with the attribute names as keys, text as string and numbers as array.
+fn json_attributes(attributes: &[NetCDFAttribute]) -> String {
+    let entries: Vec<String> = attributes.iter().map(|attribute| {
+        let values = match attribute.values.as_string() {
+            Some(text) => json_string(&text),
+            None => {
+                let values: Vec<String> = (0..attribute.values.len())
+                    .filter_map(|index| attribute.values.get(index))
+                    .map(|value| json_number(&value))
+                    .collect();
+                format!("[{}]", values.join(", "))
+            }
+        };
+
+        format!("{}: {}", json_string(&attribute.name), values)
+    }).collect();
+
+    format!("{{{}}}", entries.join(", "))
+}
+
+/// NaN and infinity are not allowed in JSON and written as null.
+fn json_number(value: &NetCDFValue) -> String {
+    match value {
+        NetCDFValue::Float(v) if !v.is_finite() => "null".to_string(),
+        NetCDFValue::Double(v) if !v.is_finite() => "null".to_string(),
+        NetCDFValue::Float(v) => v.to_string(),
+        NetCDFValue::Double(v) => v.to_string(),
+        NetCDFValue::Byte(v) => v.to_string(),
+        NetCDFValue::Char(v) => (*v as u32).to_string(),
+        NetCDFValue::Short(v) => v.to_string(),
+        NetCDFValue::Int(v) => v.to_string(),
+        NetCDFValue::UByte(v) => v.to_string(),
+        NetCDFValue::UShort(v) => v.to_string(),
+        NetCDFValue::UInt(v) => v.to_string(),
+        NetCDFValue::Int64(v) => v.to_string(),
+        NetCDFValue::UInt64(v) => v.to_string(),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut result = String::from("\"");
+
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+
+    result.push('"');
+    result
+}
@@ -0,0 +1,346 @@
+// Rust modules
+use std::collections::HashSet;
+
+// External modules
+use log::debug;
+
+// Internal modules
+use crate::netcdf::*;
+use crate::writer::{update_layout, NetCDFWriteOptions, SectionBegin};
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetCDFDimensionLength {
+    Fixed(u64),
+    Unlimited,
+}
+
+struct BuilderVariable {
+    name: String,
+    nc_type: NetCDFType,
+    dimensions: Vec<String>,
+    att_list: Vec<NetCDFAttribute>,
+    values: Option<NetCDFArray>,
+    /// scale_factor and add_offset, if the values need to be packed
+    packing: Option<(f64, f64)>,
+}
+
+/// Creates a new NetCDF data set from scratch:
+///
+/// ```
+/// use netcdfrs::prelude::*;
+/// use netcdfrs::prelude::NetCDFDimensionLength::*;
+///
+/// let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+///     .add_dimension("time", Unlimited)
+///     .add_dimension("lat", Fixed(2))
+///     .add_attribute("title", NetCDFArray::Char(b"example".to_vec()))
+///     .add_variable("temp", NetCDFType::NCFloat, &["time", "lat"])
+///     .add_variable_attribute("temp", "units", NetCDFArray::Char(b"K".to_vec()))
+///     .set_variable_data("temp", NetCDFArray::Float(vec![280.0, 281.0, 282.0, 283.0]))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(netcdf.num_of_records(), 2);
+/// ```
+///
+/// All checks are done in build(). Variables without data are filled with their fill value:
+/// the value of the _FillValue attribute or the default fill value of the type.
+pub struct NetCDFBuilder {
+    version: NetCDFVersion,
+    dimensions: Vec<(String, NetCDFDimensionLength)>,
+    att_list: Vec<NetCDFAttribute>,
+    variables: Vec<BuilderVariable>,
+    errors: Vec<String>,
+}
+
+impl NetCDFBuilder {
+    pub fn new(version: NetCDFVersion) -> NetCDFBuilder {
+        NetCDFBuilder{version, dimensions: Vec::new(), att_list: Vec::new(), variables: Vec::new(), errors: Vec::new()}
+    }
+
+    pub fn add_dimension(mut self, name: &str, length: NetCDFDimensionLength) -> NetCDFBuilder {
+        self.dimensions.push((name.to_string(), length));
+        self
+    }
+
+    /// Adds a global attribute.
+    pub fn add_attribute(mut self, name: &str, values: NetCDFArray) -> NetCDFBuilder {
+        self.att_list.push(NetCDFAttribute{name: name.to_string(), values});
+        self
+    }
+
+    pub fn add_variable(mut self, name: &str, nc_type: NetCDFType, dimensions: &[&str]) -> NetCDFBuilder {
+        self.variables.push(BuilderVariable{
+            name: name.to_string(),
+            nc_type,
+            dimensions: dimensions.iter().map(|dimension| dimension.to_string()).collect(),
+            att_list: Vec::new(),
+            values: None,
+            packing: None,
+        });
+        self
+    }
+
+    pub fn add_variable_attribute(mut self, variable: &str, name: &str, values: NetCDFArray) -> NetCDFBuilder {
+        match self.variables.iter_mut().find(|other| other.name == variable) {
+            Some(other) => other.att_list.push(NetCDFAttribute{name: name.to_string(), values}),
+            None => self.errors.push(format!("attribute '{}' refers to unknown variable '{}'", name, variable)),
+        }
+        self
+    }
+
+    /// Sets all values of a variable in row-major order. For record variables
+    /// the number of records is given by the number of values.
+    pub fn set_variable_data(mut self, variable: &str, values: NetCDFArray) -> NetCDFBuilder {
+        match self.variables.iter_mut().find(|other| other.name == variable) {
+            Some(other) => other.values = Some(values),
+            None => self.errors.push(format!("data refers to unknown variable '{}'", variable)),
+        }
+        self
+    }
+
+    /// Sets float or double values that are packed into the integer type of the variable
+    /// with round((value - add_offset) / scale_factor), NaN values are stored as fill value.
+    /// The attributes scale_factor and add_offset are added with the type of the values,
+    /// so that they are unpacked with the same precision.
+    pub fn set_variable_data_packed(mut self, variable: &str, values: NetCDFArray, scale_factor: f64, add_offset: f64)
+            -> NetCDFBuilder {
+        let attributes = match values {
+            NetCDFArray::Float(_) => Some((NetCDFArray::Float(vec![scale_factor as f32]), NetCDFArray::Float(vec![add_offset as f32]))),
+            NetCDFArray::Double(_) => Some((NetCDFArray::Double(vec![scale_factor]), NetCDFArray::Double(vec![add_offset]))),
+            _ => None,
+        };
+
+        match (self.variables.iter_mut().find(|other| other.name == variable), attributes) {
+            (Some(other), Some((scale_factor_values, add_offset_values))) => {
+                other.att_list.retain(|attribute| attribute.name != "scale_factor" && attribute.name != "add_offset");
+                other.att_list.push(NetCDFAttribute{name: "scale_factor".to_string(), values: scale_factor_values});
+                other.att_list.push(NetCDFAttribute{name: "add_offset".to_string(), values: add_offset_values});
+                other.values = Some(values);
+                other.packing = Some((scale_factor, add_offset));
+            }
+            (Some(_), None) => self.errors.push(format!("packed data for variable '{}' must be float or double, got {:?}",
+                variable, values.nc_type())),
+            (None, _) => self.errors.push(format!("data refers to unknown variable '{}'", variable)),
+        }
+        self
+    }
+
+    pub fn build(mut self) -> Result<NetCDF, NetCDFError> {
+        if let Some(error) = self.errors.first() {
+            return Err(NetCDFError::InvalidDefinition(error.clone()))
+        }
+
+        if self.version == NetCDFVersion::HDF5 {
+            return Err(NetCDFError::HDF5NotSupportetYet)
+        }
+
+        let dim_list = self.check_dimensions()?;
+        check_attributes(self.version, &self.att_list)?;
+
+        let mut var_list = Vec::new();
+        let mut names = HashSet::new();
+
+        for variable in self.variables.iter_mut() {
+            check_name(&variable.name)?;
+            check_type(self.version, &variable.name, variable.nc_type)?;
+            check_attributes(self.version, &variable.att_list)?;
+            check_fill_value(&variable.name, variable.nc_type, &variable.att_list)?;
+
+            if !names.insert(&variable.name) {
+                return Err(NetCDFError::InvalidDefinition(format!("variable '{}' is defined twice", variable.name)))
+            }
+
+            let mut dimid = Vec::new();
+
+            for (i, dimension) in variable.dimensions.iter().enumerate() {
+                let dim_id = self.dimensions.iter().position(|(name, _)| name == dimension)
+                    .ok_or_else(|| NetCDFError::InvalidDefinition(format!("variable '{}' refers to unknown dimension '{}'",
+                        variable.name, dimension)))?;
+
+                if i > 0 && self.dimensions[dim_id].1 == NetCDFDimensionLength::Unlimited {
+                    return Err(NetCDFError::InvalidDefinition(format!(
+                        "variable '{}' can only use the unlimited dimension '{}' as its first dimension",
+                        variable.name, dimension)))
+                }
+
+                dimid.push(dim_id as u32);
+            }
+
+            let offset = match self.version {
+                NetCDFVersion::CDF01 => NetCDFOffset::Pos32(0),
+                _ => NetCDFOffset::Pos64(0),
+            };
+
+            let nc_variable = NetCDFVariable{name: variable.name.clone(), dimid, att_list: variable.att_list.clone(),
+                nc_type: variable.nc_type, vsize: 0, offset};
+
+            if let (Some(values), Some((scale_factor, add_offset))) = (&variable.values, variable.packing) {
+                let packed = values.pack(variable.nc_type, scale_factor, add_offset, &nc_variable.fill_value())
+                    .map_err(|error| NetCDFError::InvalidDefinition(format!("variable '{}': {}", variable.name, error)))?;
+                variable.values = Some(packed);
+            }
+
+            if let Some(values) = &variable.values {
+                if values.nc_type() != variable.nc_type {
+                    return Err(NetCDFError::InvalidDefinition(format!("variable '{}' has type {:?}, got data of type {:?}",
+                        variable.name, variable.nc_type, values.nc_type())))
+                }
+            }
+
+            var_list.push(nc_variable);
+        }
+
+        let mut header = NetCDFHeader{version: self.version, numrecs: NetCDFStreaming::Normal(0),
+            dim_list, att_list: self.att_list, var_list};
+
+        let data = split_data(&header, self.variables)?;
+        let num_of_records = data.recs.len() as u64;
+        debug!("NetCDFBuilder::build, number of records: {}", num_of_records);
+
+        header.numrecs = NetCDFStreaming::Normal(num_of_records);
+        update_layout(&mut header, num_of_records, &NetCDFWriteOptions::default(), &SectionBegin::default())?;
+
+        Ok(NetCDF{header, data})
+    }
+
+    fn check_dimensions(&self) -> Result<Vec<NetCDFDimension>, NetCDFError> {
+        let mut result: Vec<NetCDFDimension> = Vec::new();
+
+        for (name, length) in self.dimensions.iter() {
+            check_name(name)?;
+
+            if result.iter().any(|dimension| dimension.name == *name) {
+                return Err(NetCDFError::InvalidDefinition(format!("dimension '{}' is defined twice", name)))
+            }
+
+            let length = match length {
+                NetCDFDimensionLength::Fixed(0) => {
+                    return Err(NetCDFError::InvalidDefinition(format!("dimension '{}' has length 0", name)))
+                }
+                NetCDFDimensionLength::Fixed(n) => *n,
+                NetCDFDimensionLength::Unlimited => {
+                    if result.iter().any(|dimension| dimension.length == 0) {
+                        return Err(NetCDFError::InvalidDefinition(format!(
+                            "dimension '{}': only one unlimited dimension is allowed", name)))
+                    }
+                    // The record dimension has length 0 in the header
+                    0
+                }
+            };
+
+            result.push(NetCDFDimension{name: name.clone(), length});
+        }
+
+        Ok(result)
+    }
+}
+
+/// Splits the values of all variables into the non-record data and the records.
+fn split_data(header: &NetCDFHeader, variables: Vec<BuilderVariable>) -> Result<NetCDFData, NetCDFError> {
+    let mut non_recs = Vec::new();
+    let mut record_values = Vec::new();
+
+    for (variable, builder_variable) in header.var_list.iter().zip(variables) {
+        let nvals = header.num_of_values(variable);
+
+        if header.is_record_variable(variable) {
+            let num_of_records = match &builder_variable.values {
+                None => 0,
+                Some(values) => {
+                    if values.len() % nvals != 0 {
+                        return Err(NetCDFError::InvalidDefinition(format!(
+                            "variable '{}' needs a multiple of {} values, got {}", variable.name, nvals, values.len())))
+                    }
+                    values.len() / nvals
+                }
+            };
+            record_values.push((variable, nvals, num_of_records, builder_variable.values));
+        } else {
+            let values = match builder_variable.values {
+                None => NetCDFArray::repeat(&variable.fill_value(), nvals),
+                Some(values) => {
+                    if values.len() != nvals {
+                        return Err(NetCDFError::InvalidDefinition(format!(
+                            "variable '{}' needs {} values, got {}", variable.name, nvals, values.len())))
+                    }
+                    values
+                }
+            };
+            non_recs.push(NetCDFVarData{values});
+        }
+    }
+
+    // Record variables with less data than others are filled up
+    let num_of_records = record_values.iter().map(|(_, _, n, _)| *n).max().unwrap_or(0);
+    let mut recs = Vec::new();
+
+    for record_index in 0..num_of_records {
+        let record = record_values.iter().map(|(variable, nvals, n, values)| {
+            let varslab = match values {
+                Some(values) if record_index < *n => values.slice(record_index * nvals, (record_index + 1) * nvals),
+                _ => NetCDFArray::repeat(&variable.fill_value(), *nvals),
+            };
+            NetCDFVarSlab{varslab}
+        }).collect();
+
+        recs.push(NetCDFRecord{record});
+    }
+
+    Ok(NetCDFData{non_recs, recs})
+}
+
+pub(crate) fn check_attributes(version: NetCDFVersion, att_list: &[NetCDFAttribute]) -> Result<(), NetCDFError> {
+    let mut names = HashSet::new();
+
+    for attribute in att_list.iter() {
+        check_name(&attribute.name)?;
+        check_type(version, &attribute.name, attribute.values.nc_type())?;
+
+        if !names.insert(&attribute.name) {
+            return Err(NetCDFError::InvalidDefinition(format!("attribute '{}' is defined twice", attribute.name)))
+        }
+    }
+
+    Ok(())
+}
+
+/// The _FillValue attribute must be a single value of the type of the variable.
+pub(crate) fn check_fill_value(name: &str, nc_type: NetCDFType, att_list: &[NetCDFAttribute]) -> Result<(), NetCDFError> {
+    match att_list.iter().find(|attribute| attribute.name == "_FillValue") {
+        Some(attribute) if attribute.values.nc_type() != nc_type || attribute.values.len() != 1 => {
+            Err(NetCDFError::InvalidDefinition(format!("_FillValue of variable '{}' must be a single value of type {:?}",
+                name, nc_type)))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_type(version: NetCDFVersion, name: &str, nc_type: NetCDFType) -> Result<(), NetCDFError> {
+    if nc_type.needs_cdf5() && version != NetCDFVersion::CDF05 {
+        return Err(NetCDFError::InvalidDefinition(format!("'{}' has type {:?} which needs version 5 (CDF05), got {:?}",
+            name, nc_type, version)))
+    }
+
+    Ok(())
+}
+
+/// Names must start with a letter, a digit, an underscore or a multibyte UTF-8 character,
+/// must not contain '/' or control characters and must not end with white space.
+pub(crate) fn check_name(name: &str) -> Result<(), NetCDFError> {
+    let valid = match name.chars().next() {
+        None => false,
+        Some(first) => {
+            (first.is_alphanumeric() || first == '_' || !first.is_ascii()) &&
+            !name.chars().any(|c| c == '/' || c.is_control()) &&
+            !name.ends_with(char::is_whitespace)
+        }
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(NetCDFError::InvalidDefinition(format!("invalid name: '{}'", name)))
+    }
+}
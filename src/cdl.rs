@@ -0,0 +1,1030 @@
+// Rust modules
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+// External modules
+use log::debug;
+
+// Internal modules
+use crate::netcdf::*;
+use crate::builder::{NetCDFBuilder, NetCDFDimensionLength};
+use crate::reader::NetCDFReader;
+
+// The output follows ncdump from netCDF-C, see ncdump.c and vardata.c,
+// the parser follows ncgen:
+// https://github.com/Unidata/netcdf-c/tree/main/ncdump
+// https://github.com/Unidata/netcdf-c/tree/main/ncgen
+
+/// ncdump starts a new line if a value does not fit into 80 columns.
+const MAX_LINE_LENGTH: usize = 80;
+/// Indentation of continued lines of data.
+const CONTINUATION_INDENT: &str = "    ";
+/// Significant digits for float and double values, like ncdump.
+const FLOAT_DIGITS: usize = 7;
+const DOUBLE_DIGITS: usize = 15;
+/// Newlines in text end the string and continue on a new line.
+const ATTRIBUTE_NEWLINE: &str = "\\n\",\n\t\t\t\"";
+const DATA_NEWLINE: &str = "\\n\",\n    \"";
+
+/// Options to select the output like the command line options of ncdump.
+#[derive(Debug, Clone, Default)]
+pub struct NetCDFCdlOptions {
+    /// Only write the header without the data section (ncdump -h).
+    pub header_only: bool,
+    /// Only write the data of these variables (ncdump -v), all variables if None.
+    pub variables: Option<Vec<String>>,
+}
+
+/// Writes the CDL text of the data set like "ncdump" would.
+/// The name is used in the first line, ncdump uses the file name without extension.
+pub fn write_cdl<T: Write>(writer: &mut T, name: &str, netcdf: &NetCDF) -> Result<(), NetCDFError> {
+    write_cdl_with_options(writer, name, netcdf, &NetCDFCdlOptions::default())
+}
+
+pub fn write_cdl_with_options<T: Write>(writer: &mut T, name: &str, netcdf: &NetCDF, options: &NetCDFCdlOptions)
+    -> Result<(), NetCDFError> {
+    write_cdl_data(writer, name, netcdf.header(), options, |variable| netcdf.variable_data(variable))
+}
+
+/// Like write_cdl_with_options(), but the data is read from the file only for
+/// the selected variables.
+pub fn write_cdl_reader<T: Write, R: Read + Seek>(writer: &mut T, name: &str, reader: &mut NetCDFReader<R>,
+    options: &NetCDFCdlOptions) -> Result<(), NetCDFError> {
+    let header = reader.header().clone();
+    write_cdl_data(writer, name, &header, options, |variable| reader.variable_data(variable))
+}
+
+fn write_cdl_data<T, F>(writer: &mut T, name: &str, header: &NetCDFHeader, options: &NetCDFCdlOptions,
+    mut variable_data: F) -> Result<(), NetCDFError>
+    where T: Write, F: FnMut(&str) -> Result<NetCDFVariableData, NetCDFError> {
+    if let Some(variables) = options.variables.as_ref() {
+        for variable in variables.iter() {
+            header.find_variable(variable)?;
+        }
+    }
+
+    write_header(writer, name, header)?;
+
+    if !options.header_only && !header.var_list.is_empty() {
+        writeln!(writer, "data:")?;
+
+        for variable in header.var_list.iter() {
+            let selected = match options.variables.as_ref() {
+                Some(variables) => variables.contains(&variable.name),
+                None => true,
+            };
+
+            if selected {
+                write_variable_data(writer, variable, &variable_data(&variable.name)?)?;
+            }
+        }
+    }
+
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+/// Writes only the header, like "ncdump -h". This works with the header of a
+/// NetCDFReader or NetCDFMmap without reading any data.
+pub fn write_cdl_header<T: Write>(writer: &mut T, name: &str, header: &NetCDFHeader) -> Result<(), NetCDFError> {
+    write_header(writer, name, header)?;
+    writeln!(writer, "}}")?;
+    Ok(())
+}
+
+impl NetCDF {
+    /// The CDL text of the data set, see write_cdl().
+    pub fn to_cdl(&self, name: &str) -> Result<String, NetCDFError> {
+        let mut buffer = Vec::new();
+        write_cdl(&mut buffer, name, self)?;
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
+
+fn write_header<T: Write>(writer: &mut T, name: &str, header: &NetCDFHeader) -> Result<(), NetCDFError> {
+    debug!("write_cdl, name: {}, num_of_variables: {}", name, header.var_list.len());
+
+    writeln!(writer, "netcdf {} {{", name)?;
+
+    if !header.dim_list.is_empty() {
+        writeln!(writer, "dimensions:")?;
+
+        for dimension in header.dim_list.iter() {
+            if dimension.length == 0 {
+                writeln!(writer, "\t{} = UNLIMITED ; // ({} currently)", dimension.name, header.num_of_records())?;
+            } else {
+                writeln!(writer, "\t{} = {} ;", dimension.name, dimension.length)?;
+            }
+        }
+    }
+
+    if !header.var_list.is_empty() {
+        writeln!(writer, "variables:")?;
+
+        for variable in header.var_list.iter() {
+            let dimensions: Vec<&str> = header.variable_dimensions(variable).iter()
+                .map(|dimension| dimension.name.as_str())
+                .collect();
+
+            if dimensions.is_empty() {
+                writeln!(writer, "\t{} {} ;", variable.nc_type.name(), variable.name)?;
+            } else {
+                writeln!(writer, "\t{} {}({}) ;", variable.nc_type.name(), variable.name, dimensions.join(", "))?;
+            }
+
+            for attribute in variable.att_list.iter() {
+                writeln!(writer, "\t\t{}:{} = {} ;", variable.name, attribute.name, attribute_text(&attribute.values))?;
+            }
+        }
+    }
+
+    if !header.att_list.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "// global attributes:")?;
+
+        for attribute in header.att_list.iter() {
+            writeln!(writer, "\t\t:{} = {} ;", attribute.name, attribute_text(&attribute.values))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes " name = values ;", each row of the last dimension starts a new line.
+fn write_variable_data<T: Write>(writer: &mut T, variable: &NetCDFVariable, data: &NetCDFVariableData)
+    -> Result<(), NetCDFError> {
+    // Like ncdump, record variables without records are left out
+    if data.values.is_empty() {
+        return Ok(())
+    }
+
+    let num_of_columns = data.shape.last().copied().unwrap_or(1).max(1);
+    let num_of_rows = data.values.len() / num_of_columns;
+
+    writeln!(writer)?;
+
+    let mut line = if data.shape.len() <= 1 {
+        write!(writer, " {} = ", variable.name)?;
+        variable.name.len() + 4
+    } else {
+        write!(writer, " {} =\n  ", variable.name)?;
+        2
+    };
+
+    if let NetCDFArray::Char(chars) = &data.values {
+        for (row, text) in chars.chunks(num_of_columns).enumerate() {
+            let last_row = row + 1 == num_of_rows;
+            write!(writer, "{}{}", quoted_text(text, DATA_NEWLINE), if last_row { " ;\n" } else { ",\n  " })?;
+        }
+
+        return Ok(())
+    }
+
+    let fill_value = data_fill_value(variable);
+
+    for row in 0..num_of_rows {
+        let last_row = row + 1 == num_of_rows;
+
+        for column in 0..num_of_columns {
+            let index = (row * num_of_columns) + column;
+            let value = data.values.get(index).ok_or_else(|| NetCDFError::InvalidData(format!(
+                "variable '{}' has {} values, got index {}", variable.name, data.values.len(), index)))?;
+            let mut text = match &fill_value {
+                Some(fill_value) if *fill_value == value => "_".to_string(),
+                _ => value_text(&value, false),
+            };
+
+            text.push_str(match (column + 1 == num_of_columns, last_row) {
+                (false, _) => ", ",
+                (true, false) => ",",
+                (true, true) => " ;",
+            });
+
+            // Like lput() in ncdump: start a new line if the text does not fit
+            if line + text.len() > MAX_LINE_LENGTH && text.len() > 2 {
+                write!(writer, "\n{}", CONTINUATION_INDENT)?;
+                line = CONTINUATION_INDENT.len();
+            }
+
+            write!(writer, "{}", text)?;
+            line += text.len();
+        }
+
+        if last_row {
+            writeln!(writer)?;
+        } else {
+            write!(writer, "\n  ")?;
+            line = 2;
+        }
+    }
+
+    Ok(())
+}
+
+/// Values equal to this are written as "_". Like ncdump there is no default
+/// fill value for bytes, because all byte values are commonly used.
+fn data_fill_value(variable: &NetCDFVariable) -> Option<NetCDFValue> {
+    if variable.nc_type == NetCDFType::NCByte && variable.attribute("_FillValue").is_none() {
+        None
+    } else {
+        Some(variable.fill_value())
+    }
+}
+
+/// Attribute values with the CDL type suffix, for example 1b, 2s, 3 or 4.f
+fn attribute_text(values: &NetCDFArray) -> String {
+    if let NetCDFArray::Char(chars) = values {
+        return quoted_text(chars, ATTRIBUTE_NEWLINE)
+    }
+
+    (0..values.len())
+        .filter_map(|index| values.get(index))
+        .map(|value| value_text(&value, true))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// A single value, attributes get type suffixes and floating point
+/// numbers always have a decimal point there.
+fn value_text(value: &NetCDFValue, attribute: bool) -> String {
+    let suffix = |suffix: &'static str| if attribute { suffix } else { "" };
+
+    match value {
+        NetCDFValue::Byte(v) => format!("{}{}", v, suffix("b")),
+        NetCDFValue::Char(v) => format!("'{}'", v),
+        NetCDFValue::Short(v) => format!("{}{}", v, suffix("s")),
+        NetCDFValue::Int(v) => format!("{}", v),
+        // Float data only has the suffix for NaNf and Infinityf
+        NetCDFValue::Float(v) if attribute || !v.is_finite() => format!("{}f", float_text(*v as f64, FLOAT_DIGITS, attribute)),
+        NetCDFValue::Float(v) => float_text(*v as f64, FLOAT_DIGITS, false),
+        NetCDFValue::Double(v) => float_text(*v, DOUBLE_DIGITS, attribute),
+        NetCDFValue::UByte(v) => format!("{}{}", v, suffix("UB")),
+        NetCDFValue::UShort(v) => format!("{}{}", v, suffix("US")),
+        NetCDFValue::UInt(v) => format!("{}{}", v, suffix("U")),
+        NetCDFValue::Int64(v) => format!("{}{}", v, suffix("L")),
+        NetCDFValue::UInt64(v) => format!("{}{}", v, suffix("UL")),
+    }
+}
+
+/// Formats like printf("%.*g") in C, with keep_point like "%#.*g" followed by
+/// removing trailing zeros, so there is always a decimal point: "1." or "1.e+20"
+fn float_text(value: f64, digits: usize, keep_point: bool) -> String {
+    if value.is_nan() {
+        return "NaN".to_string()
+    }
+
+    if value.is_infinite() {
+        return if value < 0.0 { "-Infinity".to_string() } else { "Infinity".to_string() }
+    }
+
+    // The exponent after rounding to the given number of digits
+    let scientific = format!("{:.*e}", digits - 1, value);
+    // Finite numbers always have an exponent like "1.5e-7" in Rust
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    let (mut number, exponent) = if exponent < -4 || exponent >= digits as i32 {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        (mantissa.to_string(), format!("e{}{:02}", sign, exponent.abs()))
+    } else {
+        (format!("{:.*}", (digits as i32 - 1 - exponent) as usize, value), String::new())
+    };
+
+    if number.contains('.') {
+        number = number.trim_end_matches('0').to_string();
+
+        if !keep_point {
+            number = number.trim_end_matches('.').to_string();
+        }
+    } else if keep_point {
+        number.push('.');
+    }
+
+    number + &exponent
+}
+
+/// Quotes and escapes text, trailing null bytes are left out. Like ncdump a line break
+/// is inserted after each newline. UTF-8 text is kept, other non ASCII bytes are
+/// written as octal numbers.
+fn quoted_text(chars: &[u8], newline: &str) -> String {
+    let end = chars.iter().rposition(|c| *c != 0).map_or(0, |i| i + 1);
+    let mut result = String::from("\"");
+
+    match std::str::from_utf8(&chars[..end]) {
+        Ok(text) => for c in text.chars() {
+            if c.is_ascii() {
+                escape_char(c as u8, newline, &mut result);
+            } else {
+                result.push(c);
+            }
+        }
+        Err(_) => for c in chars[..end].iter() {
+            escape_char(*c, newline, &mut result);
+        }
+    }
+
+    result.push('"');
+    result
+}
+
+fn escape_char(c: u8, newline: &str, result: &mut String) {
+    match c {
+        b'\x08' => result.push_str("\\b"),
+        b'\x0c' => result.push_str("\\f"),
+        b'\n' => result.push_str(newline),
+        b'\r' => result.push_str("\\r"),
+        b'\t' => result.push_str("\\t"),
+        b'\x0b' => result.push_str("\\v"),
+        b'\\' => result.push_str("\\\\"),
+        b'\'' => result.push_str("\\'"),
+        b'"' => result.push_str("\\\""),
+        c if c.is_ascii_control() || !c.is_ascii() => result.push_str(&format!("\\{:03o}", c)),
+        c => result.push(c as char),
+    }
+}
+
+/// Parses CDL text like "ncgen" and creates a data set with the given version.
+/// The data section may leave out variables or values, these are set to the fill value.
+pub fn parse_cdl(text: &str, version: NetCDFVersion) -> Result<NetCDF, NetCDFError> {
+    let tokens = tokenize(text)?;
+    let mut parser = CdlParser{tokens, position: 0, version, dimensions: Vec::new(), variables: Vec::new(),
+        att_list: Vec::new()};
+
+    parser.parse()?;
+    parser.build()
+}
+
+/// Reads a CDL file, see parse_cdl().
+pub fn load_cdl_file<T: AsRef<Path>>(path: T, version: NetCDFVersion) -> Result<NetCDF, NetCDFError> {
+    parse_cdl(&fs::read_to_string(path)?, version)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Number(String),
+    Text(Vec<u8>),
+    Symbol(char),
+    Fill,
+}
+
+#[derive(Debug, Clone)]
+enum Constant {
+    /// Integer with the type given by the suffix
+    Integer(i128, Option<NetCDFType>),
+    Real(f64, Option<NetCDFType>),
+    Text(Vec<u8>),
+    Fill,
+}
+
+struct CdlVariable {
+    name: String,
+    nc_type: NetCDFType,
+    dimensions: Vec<String>,
+    att_list: Vec<NetCDFAttribute>,
+    data: Option<Vec<Constant>>,
+}
+
+struct CdlParser {
+    /// All tokens with their line number
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    version: NetCDFVersion,
+    dimensions: Vec<(String, NetCDFDimensionLength)>,
+    variables: Vec<CdlVariable>,
+    att_list: Vec<NetCDFAttribute>,
+}
+
+const SECTIONS: [&str; 3] = ["dimensions", "variables", "data"];
+
+impl CdlParser {
+    fn parse(&mut self) -> Result<(), NetCDFError> {
+        self.expect_name("netcdf")?;
+        let name = self.name()?;
+        debug!("parse_cdl, name: {}", name);
+        self.expect_symbol('{')?;
+
+        let mut sections = SECTIONS.iter();
+
+        while !self.next_is_symbol('}') {
+            let section = self.name()?;
+            self.expect_symbol(':')?;
+
+            // The sections are optional, but must be in this order
+            if !sections.any(|other| *other == section) {
+                return Err(self.error(&format!("unexpected section '{}'", section)))
+            }
+
+            match section.as_str() {
+                "dimensions" => self.parse_dimensions()?,
+                "variables" => self.parse_variables()?,
+                _ => self.parse_data()?,
+            }
+        }
+
+        self.expect_symbol('}')?;
+
+        if self.position < self.tokens.len() {
+            return Err(self.error("text after the closing '}'"))
+        }
+
+        Ok(())
+    }
+
+    /// name = length | UNLIMITED, separated by ',' and terminated by ';'
+    fn parse_dimensions(&mut self) -> Result<(), NetCDFError> {
+        while !self.at_section_end() {
+            let name = self.name()?;
+            self.expect_symbol('=')?;
+
+            let length = match self.next() {
+                Some(Token::Name(keyword)) if keyword.eq_ignore_ascii_case("unlimited") => NetCDFDimensionLength::Unlimited,
+                Some(Token::Number(number)) => match parse_number(&number, self.version) {
+                    Some(Constant::Integer(n, None)) if n > 0 && n <= u64::MAX as i128 => NetCDFDimensionLength::Fixed(n as u64),
+                    _ => return Err(self.error(&format!("invalid length of dimension '{}': {}", name, number))),
+                },
+                _ => return Err(self.error(&format!("expected length of dimension '{}'", name))),
+            };
+
+            self.dimensions.push((name, length));
+            self.list_separator()?;
+        }
+
+        Ok(())
+    }
+
+    /// Declarations "type name(dimensions), ... ;" and attributes "variable:name = values ;".
+    /// Global attributes have no variable name.
+    fn parse_variables(&mut self) -> Result<(), NetCDFError> {
+        while !self.at_section_end() {
+            let nc_type = match self.peek() {
+                Some(Token::Name(name)) => cdl_type(name),
+                _ => None,
+            };
+
+            match nc_type {
+                // A typed attribute like "double :name = 1 ;" or "float x:name = 1 ;"
+                Some(nc_type) if self.is_attribute_at(self.position + 1) => {
+                    self.position += 1;
+                    self.parse_attribute(Some(nc_type))?;
+                }
+                Some(nc_type) => {
+                    self.position += 1;
+                    self.parse_declaration(nc_type)?;
+                }
+                None => self.parse_attribute(None)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_declaration(&mut self, nc_type: NetCDFType) -> Result<(), NetCDFError> {
+        loop {
+            let name = self.name()?;
+            let mut dimensions = Vec::new();
+
+            if self.next_is_symbol('(') {
+                self.position += 1;
+
+                loop {
+                    dimensions.push(self.name()?);
+
+                    if self.next_is_symbol(',') {
+                        self.position += 1;
+                    } else {
+                        break
+                    }
+                }
+
+                self.expect_symbol(')')?;
+            }
+
+            if self.variables.iter().any(|variable| variable.name == name) {
+                return Err(self.error(&format!("variable '{}' is defined twice", name)))
+            }
+
+            self.variables.push(CdlVariable{name, nc_type, dimensions, att_list: Vec::new(), data: None});
+
+            if self.next_is_symbol(',') {
+                self.position += 1;
+            } else {
+                return self.expect_symbol(';')
+            }
+        }
+    }
+
+    fn parse_attribute(&mut self, nc_type: Option<NetCDFType>) -> Result<(), NetCDFError> {
+        let variable = if self.next_is_symbol(':') { None } else { Some(self.name()?) };
+        self.expect_symbol(':')?;
+        let name = self.name()?;
+        self.expect_symbol('=')?;
+
+        let constants = self.constants()?;
+        let values = attribute_values(&constants, nc_type)
+            .map_err(|message| self.error(&format!("attribute '{}': {}", name, message)))?;
+        self.expect_symbol(';')?;
+
+        let attribute = NetCDFAttribute{name, values};
+
+        match variable {
+            None => self.att_list.push(attribute),
+            Some(variable) => match self.variables.iter_mut().find(|other| other.name == variable) {
+                Some(other) => other.att_list.push(attribute),
+                None => return Err(self.error(&format!("attribute of unknown variable '{}'", variable))),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// name = values ;
+    fn parse_data(&mut self) -> Result<(), NetCDFError> {
+        while !self.at_section_end() {
+            let name = self.name()?;
+            self.expect_symbol('=')?;
+            let constants = self.constants()?;
+            self.expect_symbol(';')?;
+
+            match self.variables.iter_mut().find(|variable| variable.name == name) {
+                Some(variable) => variable.data = Some(constants),
+                None => return Err(self.error(&format!("data of unknown variable '{}'", name))),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn constants(&mut self) -> Result<Vec<Constant>, NetCDFError> {
+        let mut result = Vec::new();
+
+        loop {
+            let constant = match self.next() {
+                Some(Token::Number(number)) => parse_number(&number, self.version)
+                    .ok_or_else(|| self.error(&format!("invalid number '{}'", number)))?,
+                Some(Token::Name(name)) => parse_number(&name, self.version)
+                    .ok_or_else(|| self.error(&format!("expected a value, got '{}'", name)))?,
+                Some(Token::Text(text)) => Constant::Text(text),
+                Some(Token::Fill) => Constant::Fill,
+                _ => return Err(self.error("expected a value")),
+            };
+
+            result.push(constant);
+
+            if self.next_is_symbol(',') {
+                self.position += 1;
+            } else {
+                return Ok(result)
+            }
+        }
+    }
+
+    fn build(self) -> Result<NetCDF, NetCDFError> {
+        let mut builder = NetCDFBuilder::new(self.version);
+
+        for (name, length) in self.dimensions.iter() {
+            builder = builder.add_dimension(name, *length);
+        }
+
+        for attribute in self.att_list {
+            builder = builder.add_attribute(&attribute.name, attribute.values);
+        }
+
+        for variable in self.variables.iter() {
+            let dimensions: Vec<&str> = variable.dimensions.iter().map(|name| name.as_str()).collect();
+            builder = builder.add_variable(&variable.name, variable.nc_type, &dimensions);
+
+            for attribute in variable.att_list.iter() {
+                builder = builder.add_variable_attribute(&variable.name, &attribute.name, attribute.values.clone());
+            }
+
+            if let Some(constants) = &variable.data {
+                let values = variable_values(variable, constants, &self.dimensions)
+                    .map_err(|message| NetCDFError::InvalidCdl(format!("data of variable '{}': {}", variable.name, message)))?;
+                builder = builder.set_variable_data(&variable.name, values);
+            }
+        }
+
+        builder.build()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn next_is_symbol(&self, symbol: char) -> bool {
+        self.peek() == Some(&Token::Symbol(symbol))
+    }
+
+    /// A section ends with the next section or the closing '}'.
+    fn at_section_end(&self) -> bool {
+        match (self.peek(), self.tokens.get(self.position + 1)) {
+            (Some(Token::Name(name)), Some((Token::Symbol(':'), _))) => SECTIONS.contains(&name.as_str()),
+            (Some(Token::Symbol('}')), _) | (None, _) => true,
+            _ => false,
+        }
+    }
+
+    /// True for ":name" or "variable:name".
+    fn is_attribute_at(&self, position: usize) -> bool {
+        matches!((self.tokens.get(position), self.tokens.get(position + 1)),
+            (Some((Token::Symbol(':'), _)), _) | (Some((Token::Name(_), _)), Some((Token::Symbol(':'), _))))
+    }
+
+    fn name(&mut self) -> Result<String, NetCDFError> {
+        match self.next() {
+            Some(Token::Name(name)) => Ok(name),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected a name"))
+            }
+        }
+    }
+
+    fn expect_name(&mut self, expected: &str) -> Result<(), NetCDFError> {
+        match self.next() {
+            Some(Token::Name(name)) if name == expected => Ok(()),
+            _ => {
+                self.position -= 1;
+                Err(self.error(&format!("expected '{}'", expected)))
+            }
+        }
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), NetCDFError> {
+        if self.next_is_symbol(symbol) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", symbol)))
+        }
+    }
+
+    /// ',' continues a list, ';' ends it.
+    fn list_separator(&mut self) -> Result<(), NetCDFError> {
+        match self.next() {
+            Some(Token::Symbol(',')) | Some(Token::Symbol(';')) => Ok(()),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected ',' or ';'"))
+            }
+        }
+    }
+
+    fn error(&self, message: &str) -> NetCDFError {
+        let line = self.tokens.get(self.position)
+            .or_else(|| self.tokens.last())
+            .map_or(1, |(_, line)| *line);
+
+        NetCDFError::InvalidCdl(format!("line {}: {}", line, message))
+    }
+}
+
+fn cdl_type(name: &str) -> Option<NetCDFType> {
+    match name {
+        "byte" => Some(NetCDFType::NCByte),
+        "char" => Some(NetCDFType::NCChar),
+        "short" => Some(NetCDFType::NCShort),
+        "int" | "long" | "integer" => Some(NetCDFType::NCInt),
+        "float" | "real" => Some(NetCDFType::NCFloat),
+        "double" => Some(NetCDFType::NCDouble),
+        "ubyte" => Some(NetCDFType::NCUByte),
+        "ushort" => Some(NetCDFType::NCUShort),
+        "uint" => Some(NetCDFType::NCUInt),
+        "int64" => Some(NetCDFType::NCInt64),
+        "uint64" => Some(NetCDFType::NCUInt64),
+        _ => None,
+    }
+}
+
+/// Parses numbers with an optional type suffix: 1b, 2s, 3, 4U, 5.f, 6.0 or 0x1F.
+/// NaN and Infinity are doubles, NaNf and Infinityf floats.
+fn parse_number(text: &str, version: NetCDFVersion) -> Option<Constant> {
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let sign = if negative { -1.0 } else { 1.0 };
+
+    match unsigned {
+        "NaN" => return Some(Constant::Real(f64::NAN, Some(NetCDFType::NCDouble))),
+        "NaNf" => return Some(Constant::Real(f64::NAN, Some(NetCDFType::NCFloat))),
+        "Infinity" => return Some(Constant::Real(sign * f64::INFINITY, Some(NetCDFType::NCDouble))),
+        "Infinityf" => return Some(Constant::Real(sign * f64::INFINITY, Some(NetCDFType::NCFloat))),
+        _ => {}
+    }
+
+    let is_hex = unsigned.starts_with("0x") || unsigned.starts_with("0X");
+    let (body, suffix) = if is_hex {
+        let end = unsigned[2..].find(|c: char| !c.is_ascii_hexdigit()).map_or(unsigned.len(), |i| i + 2);
+        unsigned.split_at(end)
+    } else {
+        let end = unsigned.rfind(|c: char| !c.is_ascii_alphabetic()).map_or(0, |i| i + 1);
+        unsigned.split_at(end)
+    };
+
+    let is_real = !is_hex && body.contains(['.', 'e', 'E']);
+    let suffix_type = match suffix.to_ascii_lowercase().as_str() {
+        "" => None,
+        "b" => Some(NetCDFType::NCByte),
+        "s" => Some(NetCDFType::NCShort),
+        // Like ncgen "L" is an int64 in CDF-5 and an int in the classic formats
+        "l" if version == NetCDFVersion::CDF05 => Some(NetCDFType::NCInt64),
+        "l" => Some(NetCDFType::NCInt),
+        "ll" => Some(NetCDFType::NCInt64),
+        "u" => Some(NetCDFType::NCUInt),
+        "ub" => Some(NetCDFType::NCUByte),
+        "us" => Some(NetCDFType::NCUShort),
+        "ul" | "ull" => Some(NetCDFType::NCUInt64),
+        "f" => Some(NetCDFType::NCFloat),
+        "d" => Some(NetCDFType::NCDouble),
+        _ => return None,
+    };
+
+    if is_real || matches!(suffix_type, Some(NetCDFType::NCFloat) | Some(NetCDFType::NCDouble)) {
+        let value: f64 = body.parse().ok()?;
+        return Some(Constant::Real(sign * value, suffix_type))
+    }
+
+    let value = if is_hex {
+        i128::from_str_radix(&body[2..], 16).ok()?
+    } else if body.len() > 1 && body.starts_with('0') {
+        i128::from_str_radix(&body[1..], 8).ok()?
+    } else {
+        body.parse::<i128>().ok()?
+    };
+
+    Some(Constant::Integer(if negative { -value } else { value }, suffix_type))
+}
+
+/// The type of an attribute is given explicitly, or by the first value:
+/// text is char, integers without suffix are int and reals without suffix double.
+fn attribute_values(constants: &[Constant], nc_type: Option<NetCDFType>) -> Result<NetCDFArray, String> {
+    let nc_type = nc_type.unwrap_or_else(|| match constants[0] {
+        Constant::Integer(_, Some(nc_type)) | Constant::Real(_, Some(nc_type)) => nc_type,
+        Constant::Integer(_, None) => NetCDFType::NCInt,
+        Constant::Real(_, None) => NetCDFType::NCDouble,
+        Constant::Text(_) | Constant::Fill => NetCDFType::NCChar,
+    });
+
+    let mut values = NetCDFArray::with_capacity(nc_type, constants.len());
+
+    for constant in constants.iter() {
+        match (constant, &mut values) {
+            (Constant::Text(text), NetCDFArray::Char(chars)) => chars.extend_from_slice(text),
+            (Constant::Fill, _) => return Err("'_' is not allowed in attributes".to_string()),
+            (constant, values) => values.push(&convert_constant(constant, nc_type)?),
+        }
+    }
+
+    Ok(values)
+}
+
+/// Converts the values of the data section to the type of the variable.
+/// Missing values at the end are filled with the fill value of the variable.
+fn variable_values(variable: &CdlVariable, constants: &[Constant], dimensions: &[(String, NetCDFDimensionLength)])
+    -> Result<NetCDFArray, String> {
+    let lengths: Vec<Option<u64>> = variable.dimensions.iter()
+        .map(|name| match dimensions.iter().find(|(other, _)| other == name) {
+            Some((_, NetCDFDimensionLength::Fixed(n))) => Some(*n),
+            _ => None,
+        })
+        .collect();
+
+    // Number of values in the variable or in a single record
+    let is_record = lengths.first() == Some(&None);
+    let num_of_values = lengths.iter()
+        .try_fold(1u64, |product, length| product.checked_mul(length.unwrap_or(1)))
+        .and_then(|num_of_values| usize::try_from(num_of_values).ok())
+        .ok_or_else(|| "the dimensions are too large".to_string())?;
+
+    let fill_value = variable.att_list.iter()
+        .find(|attribute| attribute.name == "_FillValue" && attribute.values.nc_type() == variable.nc_type)
+        .and_then(|attribute| attribute.values.get(0))
+        .unwrap_or_else(|| variable.nc_type.default_fill_value());
+
+    // The declared dimensions may be a lot larger than the data section
+    let mut values = NetCDFArray::with_capacity(variable.nc_type, num_of_values.min(constants.len()));
+
+    for constant in constants.iter() {
+        match (constant, &mut values) {
+            // Like ncgen each text fills complete rows of the last dimension,
+            // unless the only dimension is the unlimited one
+            (Constant::Text(text), NetCDFArray::Char(chars)) => {
+                let row_length = match lengths.last() {
+                    Some(Some(n)) => *n as usize,
+                    _ => 1,
+                };
+                let padded = text.len().div_ceil(row_length).max(1) * row_length;
+
+                chars.extend_from_slice(text);
+                chars.resize(chars.len() + padded - text.len(), 0);
+            }
+            (Constant::Fill, values) => values.push(&fill_value),
+            (constant, values) => values.push(&convert_constant(constant, variable.nc_type)?),
+        }
+    }
+
+    let expected = if is_record {
+        values.len().div_ceil(num_of_values) * num_of_values
+    } else {
+        num_of_values
+    };
+
+    if values.len() > expected {
+        return Err(format!("expected {} values, got {}", expected, values.len()))
+    }
+
+    values.extend_from(&NetCDFArray::repeat(&fill_value, expected - values.len()));
+    Ok(values)
+}
+
+fn convert_constant(constant: &Constant, nc_type: NetCDFType) -> Result<NetCDFValue, String> {
+    let out_of_range = || format!("value {:?} does not fit into type {}", constant, nc_type.name());
+
+    let integer = match constant {
+        Constant::Integer(value, _) => *value,
+        Constant::Real(value, _) => match nc_type {
+            NetCDFType::NCFloat => return Ok(NetCDFValue::Float(*value as f32)),
+            NetCDFType::NCDouble => return Ok(NetCDFValue::Double(*value)),
+            _ if value.is_finite() && value.fract() == 0.0 => *value as i128,
+            _ => return Err(out_of_range()),
+        },
+        Constant::Text(text) if text.len() == 1 => text[0] as i128,
+        _ => return Err(format!("expected a single value of type {}", nc_type.name())),
+    };
+
+    let value = match nc_type {
+        // Like ncgen bytes can be given as signed or unsigned values
+        NetCDFType::NCByte if (-128..=255).contains(&integer) => NetCDFValue::Byte(integer as u8 as i8),
+        NetCDFType::NCChar if (0..=255).contains(&integer) => NetCDFValue::Char(integer as u8 as char),
+        NetCDFType::NCShort => NetCDFValue::Short(i16::try_from(integer).map_err(|_| out_of_range())?),
+        NetCDFType::NCInt => NetCDFValue::Int(i32::try_from(integer).map_err(|_| out_of_range())?),
+        NetCDFType::NCFloat => NetCDFValue::Float(integer as f32),
+        NetCDFType::NCDouble => NetCDFValue::Double(integer as f64),
+        NetCDFType::NCUByte => NetCDFValue::UByte(u8::try_from(integer).map_err(|_| out_of_range())?),
+        NetCDFType::NCUShort => NetCDFValue::UShort(u16::try_from(integer).map_err(|_| out_of_range())?),
+        NetCDFType::NCUInt => NetCDFValue::UInt(u32::try_from(integer).map_err(|_| out_of_range())?),
+        NetCDFType::NCInt64 => NetCDFValue::Int64(i64::try_from(integer).map_err(|_| out_of_range())?),
+        NetCDFType::NCUInt64 => NetCDFValue::UInt64(u64::try_from(integer).map_err(|_| out_of_range())?),
+        _ => return Err(out_of_range()),
+    };
+
+    Ok(value)
+}
+
+fn tokenize(text: &str) -> Result<Vec<(Token, usize)>, NetCDFError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut index = 0;
+
+    let error = |line: usize, message: String| NetCDFError::InvalidCdl(format!("line {}: {}", line, message));
+
+    while index < chars.len() {
+        let c = chars[index];
+        let next = chars.get(index + 1).copied().unwrap_or('\0');
+
+        match c {
+            '\n' => {
+                line += 1;
+                index += 1;
+            }
+            c if c.is_whitespace() => index += 1,
+            '/' if next == '/' => {
+                while index < chars.len() && chars[index] != '\n' {
+                    index += 1;
+                }
+            }
+            '{' | '}' | '(' | ')' | ',' | ';' | ':' | '=' => {
+                tokens.push((Token::Symbol(c), line));
+                index += 1;
+            }
+            '"' | '\'' => {
+                let start_line = line;
+                let mut text = Vec::new();
+                index += 1;
+
+                loop {
+                    match chars.get(index) {
+                        None => return Err(error(start_line, "unterminated string".to_string())),
+                        Some(end) if *end == c => break,
+                        Some('\\') => {
+                            let (bytes, length) = unescape(&chars[index + 1..]);
+                            text.extend_from_slice(&bytes);
+                            index += length;
+                        }
+                        Some(other) => {
+                            if *other == '\n' {
+                                line += 1;
+                            }
+                            let mut buffer = [0; 4];
+                            text.extend_from_slice(other.encode_utf8(&mut buffer).as_bytes());
+                        }
+                    }
+                    index += 1;
+                }
+
+                tokens.push((Token::Text(text), start_line));
+                index += 1;
+            }
+            c if c.is_ascii_digit() || (matches!(c, '+' | '-' | '.') && (next.is_ascii_digit() || next == '.')) => {
+                let start = index;
+                index += 1;
+
+                while let Some(c) = chars.get(index) {
+                    let exponent_sign = matches!(c, '+' | '-') && matches!(chars[index - 1], 'e' | 'E') &&
+                        !chars[start..index].iter().any(|c| *c == 'x' || *c == 'X');
+
+                    if c.is_ascii_alphanumeric() || *c == '.' || exponent_sign {
+                        index += 1;
+                    } else {
+                        break
+                    }
+                }
+
+                tokens.push((Token::Number(chars[start..index].iter().collect()), line));
+            }
+            // -Infinity and -Infinityf
+            '-' if next.is_alphabetic() => {
+                let (name, length) = read_name(&chars[index + 1..]);
+                tokens.push((Token::Number(format!("-{}", name)), line));
+                index += length + 1;
+            }
+            c if c.is_alphabetic() || c == '_' || c == '\\' || !c.is_ascii() => {
+                let (name, length) = read_name(&chars[index..]);
+                tokens.push((if name == "_" { Token::Fill } else { Token::Name(name) }, line));
+                index += length;
+            }
+            c => return Err(error(line, format!("unexpected character '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a name, special characters can be escaped with a backslash.
+fn read_name(chars: &[char]) -> (String, usize) {
+    let mut name = String::new();
+    let mut index = 0;
+
+    while let Some(c) = chars.get(index) {
+        match c {
+            '\\' if index + 1 < chars.len() => {
+                name.push(chars[index + 1]);
+                index += 2;
+            }
+            c if c.is_alphanumeric() || matches!(c, '_' | '.' | '+' | '-' | '@') || !c.is_ascii() => {
+                name.push(*c);
+                index += 1;
+            }
+            _ => break,
+        }
+    }
+
+    (name, index)
+}
+
+/// Decodes the escape sequence after a backslash, returns the bytes
+/// and the number of characters used.
+fn unescape(chars: &[char]) -> (Vec<u8>, usize) {
+    let simple = match chars.first() {
+        Some('n') => Some(b'\n'),
+        Some('t') => Some(b'\t'),
+        Some('r') => Some(b'\r'),
+        Some('b') => Some(b'\x08'),
+        Some('f') => Some(b'\x0c'),
+        Some('v') => Some(b'\x0b'),
+        Some('a') => Some(b'\x07'),
+        Some('0'..='7') | Some('x') | None => None,
+        Some(c) if c.is_ascii() => Some(*c as u8),
+        Some(_) => None,
+    };
+
+    if let Some(byte) = simple {
+        return (vec![byte], 1)
+    }
+
+    let (radix, skip, max_digits) = match chars.first() {
+        Some('x') => (16, 1, 2),
+        Some('0'..='7') => (8, 0, 3),
+        Some(c) => {
+            let mut buffer = [0; 4];
+            return (c.encode_utf8(&mut buffer).as_bytes().to_vec(), 1)
+        }
+        None => return (vec![b'\\'], 0),
+    };
+
+    let digits: String = chars[skip..].iter().take(max_digits).take_while(|c| c.is_digit(radix)).collect();
+    let byte = u32::from_str_radix(&digits, radix).unwrap_or(0) as u8;
+
+    (vec![byte], skip + digits.len())
+}
@@ -3,8 +3,26 @@
 mod netcdf;
 mod reader;
 mod writer;
+mod builder;
+mod redef;
+mod time;
+mod units;
+mod cdl;
+#[cfg(feature = "mmap")]
+mod mmap;
 
 pub mod prelude {
-    pub use crate::netcdf::{NetCDF, NetCDFError};
-    pub use crate::reader::{load_file, load_reader};
+    pub use crate::netcdf::{NetCDF, NetCDFHeader, NetCDFVersion, NetCDFError, NetCDFType, NetCDFValue, NetCDFArray,
+        NetCDFDimension, NetCDFAttribute, NetCDFVariable, NetCDFVariableData, NetCDFMaskedData, NetCDFVariableSource};
+    pub use crate::reader::{load_file, load_reader, open_file, open_reader, file_version, NetCDFReader};
+    pub use crate::writer::{save_file, save_writer, save_file_with_options, save_writer_with_options, NetCDFWriteOptions,
+        open_append, open_appender, NetCDFAppender, convert_file, convert_version};
+    pub use crate::builder::{NetCDFBuilder, NetCDFDimensionLength};
+    pub use crate::redef::{open_redef, open_redefiner, NetCDFRedefiner, NetCDFFillMode};
+    pub use crate::time::{NetCDFCalendar, NetCDFDateTime, NetCDFTimeUnit, NetCDFTimeUnits};
+    pub use crate::units::NetCDFUnit;
+    pub use crate::cdl::{write_cdl, write_cdl_with_options, write_cdl_reader, write_cdl_header, parse_cdl, load_cdl_file,
+        NetCDFCdlOptions};
+    #[cfg(feature = "mmap")]
+    pub use crate::mmap::{open_file_mmap, NetCDFMmap};
 }
@@ -0,0 +1,75 @@
+
+
+// Rust modules
+use std::path::Path;
+use std::fs::File;
+use std::io;
+use std::convert::TryFrom;
+
+// External modules
+use log::info;
+use memmap2::Mmap;
+
+// Internal modules
+use crate::netcdf::*;
+use crate::reader::{read_header, decode_values, read_slice_runs};
+
+
+/// Opens a file as a memory map and parses only the header,
+/// variable data is decoded directly from the mapped bytes when requested.
+pub fn open_file_mmap<T: AsRef<Path>>(path: T) -> Result<NetCDFMmap, NetCDFError> {
+    let file_path = path.as_ref();
+    info!("mmap.rs, open_file_mmap, trying to open file: '{}'", file_path.display());
+    let file = File::open(file_path)?;
+
+    // Safety: the file must not be modified by another process while it is mapped
+    let mmap = unsafe { Mmap::map(&file)? };
+    let mut header = read_header(&mut &mmap[..])?;
+    header.resolve_streaming(mmap.len() as u64);
+    header.check_num_of_records(mmap.len() as u64)?;
+
+    Ok(NetCDFMmap{header, mmap})
+}
+
+pub struct NetCDFMmap {
+    header: NetCDFHeader,
+    mmap: Mmap,
+}
+
+impl NetCDFMmap {
+    pub fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    pub fn variable_data(&self, name: &str) -> Result<NetCDFVariableData, NetCDFError> {
+        let (_, variable) = self.header.find_variable(name)?;
+        let shape = self.header.variable_shape(variable, self.header.num_of_records() as usize);
+        let start = vec![0; shape.len()];
+        self.read_slice(name, &start, &shape, None)
+    }
+
+    /// Reads a hyperslab of a variable, like nc_get_vars in netCDF-C.
+    /// If no stride is given, all strides are 1.
+    pub fn read_slice(&self, name: &str, start: &[usize], count: &[usize], stride: Option<&[usize]>)
+        -> Result<NetCDFVariableData, NetCDFError> {
+        read_slice_runs(&self.header, name, start, count, stride, |nc_type, offset, span| {
+            let range = usize::try_from(offset).ok()
+                .and_then(|begin| Some(begin..begin.checked_add(span.checked_mul(nc_type.size_in_bytes())?)?))
+                .ok_or_else(|| NetCDFError::InvalidData(format!("offset {} does not fit into memory", offset)))?;
+
+            let bytes = self.mmap.get(range).ok_or_else(|| io::Error::new(
+                io::ErrorKind::UnexpectedEof, "variable data lies beyond the end of the file"))?;
+            Ok(decode_values(bytes, nc_type))
+        })
+    }
+}
+
+impl NetCDFVariableSource for &NetCDFMmap {
+    fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    fn variable_data(&mut self, name: &str) -> Result<NetCDFVariableData, NetCDFError> {
+        NetCDFMmap::variable_data(self, name)
+    }
+}
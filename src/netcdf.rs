@@ -6,19 +6,22 @@
 use std::io;
 use std::{fmt, fmt::Display, fmt::Formatter};
 use std::string::FromUtf8Error;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
 
 // The netCDF format is described here:
 // https://www.unidata.ucar.edu/software/netcdf/docs/file_format_specifications.html
 
 pub(crate) type OneByte = [u8; 1];
-pub(crate) type TwoBytes = [u8; 2];
 pub(crate) type FourBytes = [u8; 4];
 pub(crate) type EightBytes = [u8; 8];
 
 pub(crate) const STREAMING: FourBytes = [0xff, 0xff, 0xff, 0xff];
+pub(crate) const STREAMING64: EightBytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
 pub(crate) const ZERO: FourBytes = [0x00, 0x00, 0x00, 0x00];
 pub(crate) const VERSION1: FourBytes = [0x43, 0x44, 0x46, 0x01];
 pub(crate) const VERSION2: FourBytes = [0x43, 0x44, 0x46, 0x02];
+pub(crate) const VERSION5: FourBytes = [0x43, 0x44, 0x46, 0x05];
 pub(crate) const VERSION4: FourBytes = [0x89, 0x48, 0x44, 0x46]; // HDF 5, TODO
 
 pub(crate) const NC_DIMENSION: FourBytes = [0x00, 0x00, 0x00, 0x0a];
@@ -31,13 +34,32 @@ pub(crate) const NC_SHORT: FourBytes = [0x00, 0x00, 0x00, 0x03];
 pub(crate) const NC_INT: FourBytes = [0x00, 0x00, 0x00, 0x04];
 pub(crate) const NC_FLOAT: FourBytes = [0x00, 0x00, 0x00, 0x05];
 pub(crate) const NC_DOUBLE: FourBytes = [0x00, 0x00, 0x00, 0x06];
+pub(crate) const NC_UBYTE: FourBytes = [0x00, 0x00, 0x00, 0x07];
+pub(crate) const NC_USHORT: FourBytes = [0x00, 0x00, 0x00, 0x08];
+pub(crate) const NC_UINT: FourBytes = [0x00, 0x00, 0x00, 0x09];
+pub(crate) const NC_INT64: FourBytes = [0x00, 0x00, 0x00, 0x0a];
+pub(crate) const NC_UINT64: FourBytes = [0x00, 0x00, 0x00, 0x0b];
+
+// Default fill values, used if a variable has no _FillValue attribute
+pub(crate) const NC_FILL_BYTE: i8 = -127;
+pub(crate) const NC_FILL_CHAR: u8 = 0;
+pub(crate) const NC_FILL_SHORT: i16 = -32767;
+pub(crate) const NC_FILL_INT: i32 = -2147483647;
+pub(crate) const NC_FILL_FLOAT: f32 = 9.969_21e36;
+pub(crate) const NC_FILL_DOUBLE: f64 = 9.969_209_968_386_869e36;
+pub(crate) const NC_FILL_UBYTE: u8 = 255;
+pub(crate) const NC_FILL_USHORT: u16 = 65535;
+pub(crate) const NC_FILL_UINT: u32 = 4294967295;
+pub(crate) const NC_FILL_INT64: i64 = -9223372036854775806;
+pub(crate) const NC_FILL_UINT64: u64 = 18446744073709551614;
 
 pub struct NetCDF {
     pub(crate) header: NetCDFHeader,
     pub(crate) data: NetCDFData,
 }
 
-pub(crate) struct NetCDFHeader {
+#[derive(Debug, Clone)]
+pub struct NetCDFHeader {
     pub(crate) version: NetCDFVersion,
     pub(crate) numrecs: NetCDFStreaming,
     pub(crate) dim_list: Vec<NetCDFDimension>,
@@ -45,31 +67,325 @@ pub(crate) struct NetCDFHeader {
     pub(crate) var_list: Vec<NetCDFVariable>,
 }
 
+impl NetCDFHeader {
+    pub fn version(&self) -> NetCDFVersion {
+        self.version
+    }
+
+    pub fn num_of_records(&self) -> u64 {
+        match self.numrecs {
+            NetCDFStreaming::Streaming(n) => n,
+            NetCDFStreaming::Normal(n) => n,
+        }
+    }
+
+    pub fn num_of_dimensions(&self) -> u32 {
+        self.dim_list.len() as u32
+    }
+
+    pub fn num_of_attributes(&self) -> u32 {
+        self.att_list.len() as u32
+    }
+
+    pub fn num_of_variables(&self) -> u32 {
+        self.var_list.len() as u32
+    }
+
+    pub fn list_of_dimensions(&self) -> &[NetCDFDimension] {
+        self.dim_list.as_slice()
+    }
+
+    pub fn list_of_attributes(&self) -> &[NetCDFAttribute] {
+        self.att_list.as_slice()
+    }
+
+    pub fn list_of_variables(&self) -> &[NetCDFVariable] {
+        self.var_list.as_slice()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&NetCDFVariable> {
+        self.var_list.iter().find(|variable| variable.name == name)
+    }
+
+    /// The unlimited dimension has a length of 0 here, see num_of_records().
+    pub fn dimension(&self, name: &str) -> Option<&NetCDFDimension> {
+        self.dim_list.iter().find(|dimension| dimension.name == name)
+    }
+
+    pub fn global_attribute(&self, name: &str) -> Option<&NetCDFAttribute> {
+        self.att_list.iter().find(|attribute| attribute.name == name)
+    }
+
+    pub fn unlimited_dimension(&self) -> Option<&NetCDFDimension> {
+        self.record_dimension_id().map(|dim_id| &self.dim_list[dim_id as usize])
+    }
+
+    /// Resolves the dimension ids of a variable.
+    pub fn variable_dimensions<'a>(&'a self, variable: &NetCDFVariable) -> Vec<&'a NetCDFDimension> {
+        variable.dimid.iter().map(|dim_id| &self.dim_list[*dim_id as usize]).collect()
+    }
+
+    /// Lengths of all dimensions of a variable, the unlimited dimension
+    /// has the current number of records.
+    pub fn shape(&self, variable: &NetCDFVariable) -> Vec<usize> {
+        self.variable_shape(variable, self.num_of_records() as usize)
+    }
+
+    pub(crate) fn find_variable(&self, name: &str) -> Result<(usize, &NetCDFVariable), NetCDFError> {
+        self.var_list.iter().enumerate()
+            .find(|(_, variable)| variable.name == name)
+            .ok_or_else(|| NetCDFError::UnknownVariable(name.to_string()))
+    }
+
+    /// Lengths of all dimensions of a variable, the record dimension
+    /// has the given number of records.
+    pub(crate) fn variable_shape(&self, variable: &NetCDFVariable, num_of_records: usize) -> Vec<usize> {
+        let is_record = self.is_record_variable(variable);
+
+        variable.dimid.iter().enumerate().map(|(i, dim_id)| {
+            if is_record && i == 0 {
+                num_of_records
+            } else {
+                self.dim_list[*dim_id as usize].length as usize
+            }
+        }).collect()
+    }
+
+    /// Returns the index of the record (unlimited) dimension, if there is one.
+    /// The record dimension is the only one with length 0 in the dim_list.
+    pub(crate) fn record_dimension_id(&self) -> Option<u32> {
+        self.dim_list.iter().position(|dim| dim.length == 0).map(|index| index as u32)
+    }
+
+    /// A record variable uses the record dimension as its first dimension.
+    pub fn is_record_variable(&self, variable: &NetCDFVariable) -> bool {
+        match (self.record_dimension_id(), variable.dimid.first()) {
+            (Some(record_id), Some(first_id)) => record_id == *first_id,
+            _ => false,
+        }
+    }
+
+    /// Number of values of a non-record variable or number of values
+    /// per record of a record variable.
+    pub(crate) fn num_of_values(&self, variable: &NetCDFVariable) -> usize {
+        let skip = if self.is_record_variable(variable) { 1 } else { 0 };
+
+        // Saturates for a corrupt header, reading the values fails then
+        variable.dimid.iter().skip(skip)
+            .map(|dim_id| self.dim_list[*dim_id as usize].length as usize)
+            .fold(1, usize::saturating_mul)
+    }
+
+    /// Computes the parts of the file that need to be read for a hyperslab
+    /// (like nc_get_vars in netCDF-C): a list of byte offsets together with the number
+    /// of values to read there. Inside each part the values are stride.last() apart.
+    pub(crate) fn slice_runs(&self, variable: &NetCDFVariable, start: &[usize], count: &[usize], stride: &[usize])
+        -> Result<Vec<(u64, usize)>, NetCDFError> {
+        let num_of_dims = variable.dimid.len();
+        let shape = self.variable_shape(variable, self.num_of_records() as usize);
+        let offset = variable.offset.position();
+
+        if start.len() != num_of_dims || count.len() != num_of_dims || stride.len() != num_of_dims {
+            return Err(NetCDFError::InvalidSlice(format!(
+                "variable '{}' has {} dimensions, got start: {}, count: {}, stride: {}",
+                variable.name, num_of_dims, start.len(), count.len(), stride.len())))
+        }
+
+        for i in 0..num_of_dims {
+            if stride[i] == 0 {
+                return Err(NetCDFError::InvalidSlice(format!("stride must not be 0 in dimension {}", i)))
+            }
+
+            // Index of the last value, None if it does not even fit into an usize
+            let last = count[i].saturating_sub(1).checked_mul(stride[i])
+                .and_then(|distance| distance.checked_add(start[i]));
+            let in_range = matches!(last, Some(last) if last < shape[i]);
+
+            if count[i] > 0 && !in_range {
+                return Err(NetCDFError::InvalidSlice(format!(
+                    "dimension {} has length {}, got start: {}, count: {}, stride: {}",
+                    i, shape[i], start[i], count[i], stride[i])))
+            }
+        }
+
+        if num_of_dims == 0 {
+            // Scalar variable
+            return Ok(vec![(offset, 1)])
+        }
+
+        if count.contains(&0) {
+            return Ok(Vec::new())
+        }
+
+        if count.iter().try_fold(1usize, |product, n| product.checked_mul(*n)).is_none() {
+            return Err(NetCDFError::InvalidSlice(format!("too many values in slice with count: {:?}", count)))
+        }
+
+        let too_large = || NetCDFError::InvalidData(format!("variable '{}' is too large for 64 bit offsets", variable.name));
+
+        // Distance in bytes between two neighbouring values of each dimension
+        let is_record = self.is_record_variable(variable);
+        let mut dim_bytes = vec![0; num_of_dims];
+        let mut product = variable.nc_type.size_in_bytes() as u64;
+
+        for i in (0..num_of_dims).rev() {
+            if is_record && i == 0 {
+                dim_bytes[i] = self.record_size();
+            } else {
+                dim_bytes[i] = product;
+                product = product.checked_mul(shape[i] as u64).ok_or_else(too_large)?;
+            }
+        }
+
+        // The last dimension is read in one go, unless it is the record dimension
+        // where neighbouring values are one record apart.
+        let (outer_dims, run_length) = if is_record && num_of_dims == 1 {
+            (1, 1)
+        } else {
+            (num_of_dims - 1, count[num_of_dims - 1])
+        };
+
+        let mut result = Vec::new();
+        let mut index = vec![0; outer_dims];
+
+        loop {
+            let mut position = offset;
+
+            for i in 0..num_of_dims {
+                let dim_index = if i < outer_dims { index[i] } else { 0 };
+                // The index is inside of the shape, see above
+                let dim_position = ((start[i] + (dim_index * stride[i])) as u64).checked_mul(dim_bytes[i]);
+                position = dim_position.and_then(|dim_position| position.checked_add(dim_position))
+                    .ok_or_else(too_large)?;
+            }
+
+            result.push((position, run_length));
+
+            // Advance to the next index of the outer dimensions
+            let mut i = outer_dims;
+
+            loop {
+                if i == 0 {
+                    return Ok(result)
+                }
+
+                i -= 1;
+                index[i] += 1;
+
+                if index[i] < count[i] {
+                    break
+                }
+
+                index[i] = 0;
+            }
+        }
+    }
+
+    /// Start of the record section, if there are any record variables.
+    pub(crate) fn record_begin(&self) -> Option<u64> {
+        self.var_list.iter()
+            .filter(|variable| self.is_record_variable(variable))
+            .map(|variable| variable.offset.position())
+            .min()
+    }
+
+    /// If numrecs is STREAMING, computes the number of records from the
+    /// size of the file. Incomplete records at the end are ignored.
+    pub(crate) fn resolve_streaming(&mut self, file_size: u64) {
+        if let NetCDFStreaming::Streaming(_) = self.numrecs {
+            let record_size = self.record_size();
+
+            let num_of_records = match self.record_begin() {
+                Some(begin) if record_size > 0 && file_size > begin => (file_size - begin) / record_size,
+                _ => 0,
+            };
+
+            self.numrecs = NetCDFStreaming::Streaming(num_of_records);
+        }
+    }
+
+    /// Checks that the number of records fits into a file of the given size,
+    /// before any memory is allocated for them. Only the start of the last
+    /// record needs to be inside of the file.
+    pub(crate) fn check_num_of_records(&self, file_size: u64) -> Result<(), NetCDFError> {
+        let num_of_records = self.num_of_records();
+
+        let record_begin = match self.record_begin() {
+            Some(record_begin) if num_of_records > 0 => record_begin,
+            _ => return Ok(()),
+        };
+
+        let last_record = (num_of_records - 1).checked_mul(self.record_size())
+            .and_then(|size| size.checked_add(record_begin));
+
+        match last_record {
+            Some(last_record) if last_record < file_size => Ok(()),
+            _ => Err(NetCDFError::InvalidData(format!("{} records do not fit into a file of {} bytes",
+                num_of_records, file_size))),
+        }
+    }
+
+    /// Size of the values of a non-record variable or of one record of a record variable
+    /// in bytes, without padding. This is computed from the dimensions, because in versions
+    /// 1 and 2 the vsize of a variable that does not fit into 32 bits is stored as 2^32 - 1.
+    pub(crate) fn data_size(&self, variable: &NetCDFVariable) -> u64 {
+        (self.num_of_values(variable) as u64).saturating_mul(variable.nc_type.size_in_bytes() as u64)
+    }
+
+    /// Size of one record in bytes: the sum of the padded sizes of all record variables.
+    pub(crate) fn record_size(&self) -> u64 {
+        let pads_records = self.pads_records();
+
+        self.var_list.iter()
+            .filter(|variable| self.is_record_variable(variable))
+            .map(|variable| if pads_records { padded_size(self.data_size(variable)) } else { self.data_size(variable) })
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Whether the values of the record variables are padded in each record.
+    /// Special case from the spec: a single record variable is not padded.
+    pub(crate) fn pads_records(&self) -> bool {
+        self.var_list.iter()
+            .filter(|variable| self.is_record_variable(variable))
+            .count() > 1
+    }
+}
+
+/// Values in the file are padded to a multiple of 4 bytes.
+pub(crate) fn padded_size(size_in_bytes: u64) -> u64 {
+    size_in_bytes.saturating_add(3) & !3
+}
+
 impl Display for NetCDF {
     fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), fmt::Error> {
         let version = match self.header.version {
             NetCDFVersion:: CDF01 => "1 (CDF01)",
             NetCDFVersion:: CDF02 => "2 (CDF02)",
+            NetCDFVersion:: CDF05 => "5 (CDF05)",
             NetCDFVersion:: HDF5 => "4 (HDF5)",
         };
-        write!(formatter, "Version: {}\n", version)
+        writeln!(formatter, "Version: {}", version)
     }
 }
 
-#[derive(Debug)]
-pub(crate) enum NetCDFVersion {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetCDFVersion {
     CDF01,
     CDF02,
+    CDF05,
     HDF5,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) enum NetCDFStreaming {
-    Streaming,
-    Normal(u32),
+    /// numrecs is STREAMING in the file, the number of records
+    /// is computed from the size of the file.
+    Streaming(u64),
+    Normal(u64),
 }
 
-#[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum NetCDFType {
     NCByte,
     NCChar,
@@ -77,47 +393,688 @@ pub enum NetCDFType {
     NCInt,
     NCFloat,
     NCDouble,
+    NCUByte,
+    NCUShort,
+    NCUInt,
+    NCInt64,
+    NCUInt64,
 }
 
-#[derive(Debug)]
+impl NetCDFType {
+    /// The unsigned and 64 bit integer types are only allowed in CDF-5.
+    pub(crate) fn needs_cdf5(&self) -> bool {
+        matches!(self, NetCDFType::NCUByte | NetCDFType::NCUShort | NetCDFType::NCUInt |
+            NetCDFType::NCInt64 | NetCDFType::NCUInt64)
+    }
+
+    /// The name of the type in CDL, like "float" or "uint64".
+    pub fn name(&self) -> &'static str {
+        match self {
+            NetCDFType::NCByte => "byte",
+            NetCDFType::NCChar => "char",
+            NetCDFType::NCShort => "short",
+            NetCDFType::NCInt => "int",
+            NetCDFType::NCFloat => "float",
+            NetCDFType::NCDouble => "double",
+            NetCDFType::NCUByte => "ubyte",
+            NetCDFType::NCUShort => "ushort",
+            NetCDFType::NCUInt => "uint",
+            NetCDFType::NCInt64 => "int64",
+            NetCDFType::NCUInt64 => "uint64",
+        }
+    }
+
+    /// Size of a single value of this type in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        match self {
+            NetCDFType::NCByte => 1,
+            NetCDFType::NCChar => 1,
+            NetCDFType::NCShort => 2,
+            NetCDFType::NCInt => 4,
+            NetCDFType::NCFloat => 4,
+            NetCDFType::NCDouble => 8,
+            NetCDFType::NCUByte => 1,
+            NetCDFType::NCUShort => 2,
+            NetCDFType::NCUInt => 4,
+            NetCDFType::NCInt64 => 8,
+            NetCDFType::NCUInt64 => 8,
+        }
+    }
+
+    /// The fill value defined by the spec, used if a variable has no _FillValue attribute.
+    pub fn default_fill_value(&self) -> NetCDFValue {
+        match self {
+            NetCDFType::NCByte => NetCDFValue::Byte(NC_FILL_BYTE),
+            NetCDFType::NCChar => NetCDFValue::Char(NC_FILL_CHAR as char),
+            NetCDFType::NCShort => NetCDFValue::Short(NC_FILL_SHORT),
+            NetCDFType::NCInt => NetCDFValue::Int(NC_FILL_INT),
+            NetCDFType::NCFloat => NetCDFValue::Float(NC_FILL_FLOAT),
+            NetCDFType::NCDouble => NetCDFValue::Double(NC_FILL_DOUBLE),
+            NetCDFType::NCUByte => NetCDFValue::UByte(NC_FILL_UBYTE),
+            NetCDFType::NCUShort => NetCDFValue::UShort(NC_FILL_USHORT),
+            NetCDFType::NCUInt => NetCDFValue::UInt(NC_FILL_UINT),
+            NetCDFType::NCInt64 => NetCDFValue::Int64(NC_FILL_INT64),
+            NetCDFType::NCUInt64 => NetCDFValue::UInt64(NC_FILL_UINT64),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum NetCDFValue {
-    Byte(u8),
+    Byte(i8),
     Char(char),
     Short(i16),
     Int(i32),
     Float(f32),
     Double(f64),
+    UByte(u8),
+    UShort(u16),
+    UInt(u32),
+    Int64(i64),
+    UInt64(u64),
 }
 
-#[derive(Debug)]
+impl NetCDFValue {
+    pub fn nc_type(&self) -> NetCDFType {
+        match self {
+            NetCDFValue::Byte(_) => NetCDFType::NCByte,
+            NetCDFValue::Char(_) => NetCDFType::NCChar,
+            NetCDFValue::Short(_) => NetCDFType::NCShort,
+            NetCDFValue::Int(_) => NetCDFType::NCInt,
+            NetCDFValue::Float(_) => NetCDFType::NCFloat,
+            NetCDFValue::Double(_) => NetCDFType::NCDouble,
+            NetCDFValue::UByte(_) => NetCDFType::NCUByte,
+            NetCDFValue::UShort(_) => NetCDFType::NCUShort,
+            NetCDFValue::UInt(_) => NetCDFType::NCUInt,
+            NetCDFValue::Int64(_) => NetCDFType::NCInt64,
+            NetCDFValue::UInt64(_) => NetCDFType::NCUInt64,
+        }
+    }
+
+    /// The value as a double, 64 bit integers may lose precision.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            NetCDFValue::Byte(x) => *x as f64,
+            NetCDFValue::Char(x) => *x as u32 as f64,
+            NetCDFValue::Short(x) => *x as f64,
+            NetCDFValue::Int(x) => *x as f64,
+            NetCDFValue::Float(x) => *x as f64,
+            NetCDFValue::Double(x) => *x,
+            NetCDFValue::UByte(x) => *x as f64,
+            NetCDFValue::UShort(x) => *x as f64,
+            NetCDFValue::UInt(x) => *x as f64,
+            NetCDFValue::Int64(x) => *x as f64,
+            NetCDFValue::UInt64(x) => *x as f64,
+        }
+    }
+
+    /// Values of the same type are compared exactly, all others as doubles.
+    pub(crate) fn compare(&self, other: &NetCDFValue) -> Option<Ordering> {
+        match (self, other) {
+            (NetCDFValue::Byte(a), NetCDFValue::Byte(b)) => a.partial_cmp(b),
+            (NetCDFValue::Char(a), NetCDFValue::Char(b)) => a.partial_cmp(b),
+            (NetCDFValue::Short(a), NetCDFValue::Short(b)) => a.partial_cmp(b),
+            (NetCDFValue::Int(a), NetCDFValue::Int(b)) => a.partial_cmp(b),
+            (NetCDFValue::Float(a), NetCDFValue::Float(b)) => a.partial_cmp(b),
+            (NetCDFValue::Double(a), NetCDFValue::Double(b)) => a.partial_cmp(b),
+            (NetCDFValue::UByte(a), NetCDFValue::UByte(b)) => a.partial_cmp(b),
+            (NetCDFValue::UShort(a), NetCDFValue::UShort(b)) => a.partial_cmp(b),
+            (NetCDFValue::UInt(a), NetCDFValue::UInt(b)) => a.partial_cmp(b),
+            (NetCDFValue::Int64(a), NetCDFValue::Int64(b)) => a.partial_cmp(b),
+            (NetCDFValue::UInt64(a), NetCDFValue::UInt64(b)) => a.partial_cmp(b),
+            _ => self.to_f64().partial_cmp(&other.to_f64()),
+        }
+    }
+
+    /// Like ==, but NaN matches NaN.
+    pub(crate) fn matches(&self, other: &NetCDFValue) -> bool {
+        match self.compare(other) {
+            Some(ordering) => ordering == Ordering::Equal,
+            None => self.to_f64().is_nan() && other.to_f64().is_nan(),
+        }
+    }
+}
+
+/// Typed storage for the values of an attribute or a variable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetCDFArray {
+    Byte(Vec<i8>),
+    Char(Vec<u8>),
+    Short(Vec<i16>),
+    Int(Vec<i32>),
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    UByte(Vec<u8>),
+    UShort(Vec<u16>),
+    UInt(Vec<u32>),
+    Int64(Vec<i64>),
+    UInt64(Vec<u64>),
+}
+
+impl NetCDFArray {
+    /// An array of the given length with all entries set to value,
+    /// used for fill values.
+    pub(crate) fn repeat(value: &NetCDFValue, len: usize) -> NetCDFArray {
+        match value {
+            NetCDFValue::Byte(x) => NetCDFArray::Byte(vec![*x; len]),
+            NetCDFValue::Char(x) => NetCDFArray::Char(vec![*x as u8; len]),
+            NetCDFValue::Short(x) => NetCDFArray::Short(vec![*x; len]),
+            NetCDFValue::Int(x) => NetCDFArray::Int(vec![*x; len]),
+            NetCDFValue::Float(x) => NetCDFArray::Float(vec![*x; len]),
+            NetCDFValue::Double(x) => NetCDFArray::Double(vec![*x; len]),
+            NetCDFValue::UByte(x) => NetCDFArray::UByte(vec![*x; len]),
+            NetCDFValue::UShort(x) => NetCDFArray::UShort(vec![*x; len]),
+            NetCDFValue::UInt(x) => NetCDFArray::UInt(vec![*x; len]),
+            NetCDFValue::Int64(x) => NetCDFArray::Int64(vec![*x; len]),
+            NetCDFValue::UInt64(x) => NetCDFArray::UInt64(vec![*x; len]),
+        }
+    }
+
+    /// Packs float or double values into an integer type, the reverse of
+    /// NetCDFVariable::unpack_data(): round((value - add_offset) / scale_factor).
+    /// NaN values are stored as fill_value.
+    pub fn pack(&self, nc_type: NetCDFType, scale_factor: f64, add_offset: f64, fill_value: &NetCDFValue)
+            -> Result<NetCDFArray, NetCDFError> {
+        let values: Vec<f64> = match self {
+            NetCDFArray::Float(v) => v.iter().map(|value| *value as f64).collect(),
+            NetCDFArray::Double(v) => v.clone(),
+            _ => return Err(NetCDFError::InvalidData(format!("can only pack float or double values, got {:?}", self.nc_type())))
+        };
+
+        if fill_value.nc_type() != nc_type {
+            return Err(NetCDFError::InvalidData(format!("fill value {:?} does not match type {:?}", fill_value, nc_type)))
+        }
+
+        if scale_factor == 0.0 || !scale_factor.is_finite() {
+            return Err(NetCDFError::InvalidData(format!("invalid scale_factor: {}", scale_factor)))
+        }
+
+        let packed: Vec<Option<i128>> = values.iter().map(|value| {
+            if value.is_nan() {
+                None
+            } else {
+                Some(((value - add_offset) / scale_factor).round() as i128)
+            }
+        }).collect();
+
+        match fill_value {
+            NetCDFValue::Byte(fill) => Ok(NetCDFArray::Byte(pack_values(&packed, *fill)?)),
+            NetCDFValue::Short(fill) => Ok(NetCDFArray::Short(pack_values(&packed, *fill)?)),
+            NetCDFValue::Int(fill) => Ok(NetCDFArray::Int(pack_values(&packed, *fill)?)),
+            NetCDFValue::UByte(fill) => Ok(NetCDFArray::UByte(pack_values(&packed, *fill)?)),
+            NetCDFValue::UShort(fill) => Ok(NetCDFArray::UShort(pack_values(&packed, *fill)?)),
+            NetCDFValue::UInt(fill) => Ok(NetCDFArray::UInt(pack_values(&packed, *fill)?)),
+            NetCDFValue::Int64(fill) => Ok(NetCDFArray::Int64(pack_values(&packed, *fill)?)),
+            NetCDFValue::UInt64(fill) => Ok(NetCDFArray::UInt64(pack_values(&packed, *fill)?)),
+            _ => Err(NetCDFError::InvalidData(format!("can only pack into integer types, got {:?}", nc_type)))
+        }
+    }
+
+    /// Copies the values from begin to end into a new array.
+    pub(crate) fn slice(&self, begin: usize, end: usize) -> NetCDFArray {
+        match self {
+            NetCDFArray::Byte(v) => NetCDFArray::Byte(v[begin..end].to_vec()),
+            NetCDFArray::Char(v) => NetCDFArray::Char(v[begin..end].to_vec()),
+            NetCDFArray::Short(v) => NetCDFArray::Short(v[begin..end].to_vec()),
+            NetCDFArray::Int(v) => NetCDFArray::Int(v[begin..end].to_vec()),
+            NetCDFArray::Float(v) => NetCDFArray::Float(v[begin..end].to_vec()),
+            NetCDFArray::Double(v) => NetCDFArray::Double(v[begin..end].to_vec()),
+            NetCDFArray::UByte(v) => NetCDFArray::UByte(v[begin..end].to_vec()),
+            NetCDFArray::UShort(v) => NetCDFArray::UShort(v[begin..end].to_vec()),
+            NetCDFArray::UInt(v) => NetCDFArray::UInt(v[begin..end].to_vec()),
+            NetCDFArray::Int64(v) => NetCDFArray::Int64(v[begin..end].to_vec()),
+            NetCDFArray::UInt64(v) => NetCDFArray::UInt64(v[begin..end].to_vec()),
+        }
+    }
+
+    pub(crate) fn with_capacity(nc_type: NetCDFType, capacity: usize) -> NetCDFArray {
+        match nc_type {
+            NetCDFType::NCByte => NetCDFArray::Byte(Vec::with_capacity(capacity)),
+            NetCDFType::NCChar => NetCDFArray::Char(Vec::with_capacity(capacity)),
+            NetCDFType::NCShort => NetCDFArray::Short(Vec::with_capacity(capacity)),
+            NetCDFType::NCInt => NetCDFArray::Int(Vec::with_capacity(capacity)),
+            NetCDFType::NCFloat => NetCDFArray::Float(Vec::with_capacity(capacity)),
+            NetCDFType::NCDouble => NetCDFArray::Double(Vec::with_capacity(capacity)),
+            NetCDFType::NCUByte => NetCDFArray::UByte(Vec::with_capacity(capacity)),
+            NetCDFType::NCUShort => NetCDFArray::UShort(Vec::with_capacity(capacity)),
+            NetCDFType::NCUInt => NetCDFArray::UInt(Vec::with_capacity(capacity)),
+            NetCDFType::NCInt64 => NetCDFArray::Int64(Vec::with_capacity(capacity)),
+            NetCDFType::NCUInt64 => NetCDFArray::UInt64(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Returns every step-th value.
+    pub(crate) fn step_by(&self, step: usize) -> NetCDFArray {
+        match self {
+            NetCDFArray::Byte(v) => NetCDFArray::Byte(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::Char(v) => NetCDFArray::Char(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::Short(v) => NetCDFArray::Short(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::Int(v) => NetCDFArray::Int(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::Float(v) => NetCDFArray::Float(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::Double(v) => NetCDFArray::Double(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::UByte(v) => NetCDFArray::UByte(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::UShort(v) => NetCDFArray::UShort(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::UInt(v) => NetCDFArray::UInt(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::Int64(v) => NetCDFArray::Int64(v.iter().step_by(step).cloned().collect()),
+            NetCDFArray::UInt64(v) => NetCDFArray::UInt64(v.iter().step_by(step).cloned().collect()),
+        }
+    }
+
+    /// Appends all values of other, both arrays must have the same type.
+    pub(crate) fn extend_from(&mut self, other: &NetCDFArray) {
+        match (self, other) {
+            (NetCDFArray::Byte(v1), NetCDFArray::Byte(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::Char(v1), NetCDFArray::Char(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::Short(v1), NetCDFArray::Short(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::Int(v1), NetCDFArray::Int(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::Float(v1), NetCDFArray::Float(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::Double(v1), NetCDFArray::Double(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::UByte(v1), NetCDFArray::UByte(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::UShort(v1), NetCDFArray::UShort(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::UInt(v1), NetCDFArray::UInt(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::Int64(v1), NetCDFArray::Int64(v2)) => v1.extend_from_slice(v2),
+            (NetCDFArray::UInt64(v1), NetCDFArray::UInt64(v2)) => v1.extend_from_slice(v2),
+            (v1, v2) => panic!("NetCDFArray::extend_from, type mismatch: {:?} and {:?}", v1.nc_type(), v2.nc_type()),
+        }
+    }
+
+    /// Appends a single value, which must have the type of the array.
+    pub(crate) fn push(&mut self, value: &NetCDFValue) {
+        match (self, value) {
+            (NetCDFArray::Byte(v), NetCDFValue::Byte(x)) => v.push(*x),
+            (NetCDFArray::Char(v), NetCDFValue::Char(x)) => v.push(*x as u8),
+            (NetCDFArray::Short(v), NetCDFValue::Short(x)) => v.push(*x),
+            (NetCDFArray::Int(v), NetCDFValue::Int(x)) => v.push(*x),
+            (NetCDFArray::Float(v), NetCDFValue::Float(x)) => v.push(*x),
+            (NetCDFArray::Double(v), NetCDFValue::Double(x)) => v.push(*x),
+            (NetCDFArray::UByte(v), NetCDFValue::UByte(x)) => v.push(*x),
+            (NetCDFArray::UShort(v), NetCDFValue::UShort(x)) => v.push(*x),
+            (NetCDFArray::UInt(v), NetCDFValue::UInt(x)) => v.push(*x),
+            (NetCDFArray::Int64(v), NetCDFValue::Int64(x)) => v.push(*x),
+            (NetCDFArray::UInt64(v), NetCDFValue::UInt64(x)) => v.push(*x),
+            (v, x) => panic!("NetCDFArray::push, type mismatch: {:?} and {:?}", v.nc_type(), x.nc_type()),
+        }
+    }
+
+    pub fn nc_type(&self) -> NetCDFType {
+        match self {
+            NetCDFArray::Byte(_) => NetCDFType::NCByte,
+            NetCDFArray::Char(_) => NetCDFType::NCChar,
+            NetCDFArray::Short(_) => NetCDFType::NCShort,
+            NetCDFArray::Int(_) => NetCDFType::NCInt,
+            NetCDFArray::Float(_) => NetCDFType::NCFloat,
+            NetCDFArray::Double(_) => NetCDFType::NCDouble,
+            NetCDFArray::UByte(_) => NetCDFType::NCUByte,
+            NetCDFArray::UShort(_) => NetCDFType::NCUShort,
+            NetCDFArray::UInt(_) => NetCDFType::NCUInt,
+            NetCDFArray::Int64(_) => NetCDFType::NCInt64,
+            NetCDFArray::UInt64(_) => NetCDFType::NCUInt64,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            NetCDFArray::Byte(v) => v.len(),
+            NetCDFArray::Char(v) => v.len(),
+            NetCDFArray::Short(v) => v.len(),
+            NetCDFArray::Int(v) => v.len(),
+            NetCDFArray::Float(v) => v.len(),
+            NetCDFArray::Double(v) => v.len(),
+            NetCDFArray::UByte(v) => v.len(),
+            NetCDFArray::UShort(v) => v.len(),
+            NetCDFArray::UInt(v) => v.len(),
+            NetCDFArray::Int64(v) => v.len(),
+            NetCDFArray::UInt64(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a single value, or None if the index is out of bounds.
+    pub fn get(&self, index: usize) -> Option<NetCDFValue> {
+        match self {
+            NetCDFArray::Byte(v) => v.get(index).map(|x| NetCDFValue::Byte(*x)),
+            NetCDFArray::Char(v) => v.get(index).map(|x| NetCDFValue::Char(*x as char)),
+            NetCDFArray::Short(v) => v.get(index).map(|x| NetCDFValue::Short(*x)),
+            NetCDFArray::Int(v) => v.get(index).map(|x| NetCDFValue::Int(*x)),
+            NetCDFArray::Float(v) => v.get(index).map(|x| NetCDFValue::Float(*x)),
+            NetCDFArray::Double(v) => v.get(index).map(|x| NetCDFValue::Double(*x)),
+            NetCDFArray::UByte(v) => v.get(index).map(|x| NetCDFValue::UByte(*x)),
+            NetCDFArray::UShort(v) => v.get(index).map(|x| NetCDFValue::UShort(*x)),
+            NetCDFArray::UInt(v) => v.get(index).map(|x| NetCDFValue::UInt(*x)),
+            NetCDFArray::Int64(v) => v.get(index).map(|x| NetCDFValue::Int64(*x)),
+            NetCDFArray::UInt64(v) => v.get(index).map(|x| NetCDFValue::UInt64(*x)),
+        }
+    }
+
+    pub fn as_i8_slice(&self) -> Option<&[i8]> {
+        match self {
+            NetCDFArray::Byte(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_char_slice(&self) -> Option<&[u8]> {
+        match self {
+            NetCDFArray::Char(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i16_slice(&self) -> Option<&[i16]> {
+        match self {
+            NetCDFArray::Short(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32_slice(&self) -> Option<&[i32]> {
+        match self {
+            NetCDFArray::Int(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32_slice(&self) -> Option<&[f32]> {
+        match self {
+            NetCDFArray::Float(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64_slice(&self) -> Option<&[f64]> {
+        match self {
+            NetCDFArray::Double(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_u8_slice(&self) -> Option<&[u8]> {
+        match self {
+            NetCDFArray::UByte(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_u16_slice(&self) -> Option<&[u16]> {
+        match self {
+            NetCDFArray::UShort(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_u32_slice(&self) -> Option<&[u32]> {
+        match self {
+            NetCDFArray::UInt(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64_slice(&self) -> Option<&[i64]> {
+        match self {
+            NetCDFArray::Int64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64_slice(&self) -> Option<&[u64]> {
+        match self {
+            NetCDFArray::UInt64(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Text of a char array, trailing null bytes are removed.
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            NetCDFArray::Char(v) => {
+                let end = v.iter().rposition(|c| *c != 0).map_or(0, |i| i + 1);
+                Some(String::from_utf8_lossy(&v[..end]).into_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// All values of a variable in row-major order together with its shape.
+/// For record variables the first entry of the shape is the number of records.
+#[derive(Debug, PartialEq)]
+pub struct NetCDFVariableData {
+    pub shape: Vec<usize>,
+    pub values: NetCDFArray,
+}
+
+/// Variable data with a mask: masked values are fill values, missing values
+/// or outside of the valid range, see NetCDFVariable::mask().
+#[derive(Debug, PartialEq)]
+pub struct NetCDFMaskedData {
+    pub shape: Vec<usize>,
+    pub values: NetCDFArray,
+    /// true if the value at the same index is masked.
+    pub mask: Vec<bool>,
+}
+
+impl NetCDFMaskedData {
+    /// The value at index, None if it is masked.
+    pub fn get(&self, index: usize) -> Option<NetCDFValue> {
+        match self.mask.get(index) {
+            Some(false) => self.values.get(index),
+            _ => None,
+        }
+    }
+
+    /// All values with the masked ones set to None, for example
+    /// `data.to_options(NetCDFArray::as_f32_slice)`.
+    /// Returns None if the values are not of type T.
+    pub fn to_options<T: Copy>(&self, as_slice: impl Fn(&NetCDFArray) -> Option<&[T]>) -> Option<Vec<Option<T>>> {
+        let values = as_slice(&self.values)?;
+
+        Some(values.iter().zip(self.mask.iter())
+            .map(|(value, masked)| if *masked { None } else { Some(*value) })
+            .collect())
+    }
+
+    /// All values as doubles with the masked ones set to None.
+    pub fn to_f64_options(&self) -> Vec<Option<f64>> {
+        (0..self.values.len()).map(|index| self.get(index).map(|value| value.to_f64())).collect()
+    }
+
+    pub fn num_of_masked(&self) -> usize {
+        self.mask.iter().filter(|masked| **masked).count()
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct NetCDFDimension {
     pub name: String,
-    pub length: u32,
+    pub length: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NetCDFAttribute {
     pub name: String,
-    // TODO: Change from vec of enums to enums of vec
-    pub values: Vec<NetCDFValue>,
+    pub values: NetCDFArray,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NetCDFVariable {
     pub name: String,
     pub dimid: Vec<u32>,
     pub att_list: Vec<NetCDFAttribute>,
     pub nc_type: NetCDFType,
-    pub(crate) vsize: u32,
+    pub(crate) vsize: u64,
     pub(crate) offset: NetCDFOffset,
 }
 
-#[derive(Debug)]
+impl NetCDFVariable {
+    pub fn attribute(&self, name: &str) -> Option<&NetCDFAttribute> {
+        self.att_list.iter().find(|attribute| attribute.name == name)
+    }
+
+    /// The value of the _FillValue attribute, or the default fill value of the type
+    /// if there is no such attribute or if it does not match the type of the variable.
+    pub fn fill_value(&self) -> NetCDFValue {
+        self.attribute("_FillValue")
+            .filter(|attribute| attribute.values.nc_type() == self.nc_type)
+            .and_then(|attribute| attribute.values.get(0))
+            .unwrap_or_else(|| self.nc_type.default_fill_value())
+    }
+
+    /// True if value is the fill value of this variable, i.e. it was never written.
+    /// A NaN fill value matches all NaN values.
+    pub fn is_fill_value(&self, value: &NetCDFValue) -> bool {
+        self.fill_value().matches(value)
+    }
+
+    /// True if value is neither a fill value nor a missing_value and
+    /// inside of valid_range or valid_min / valid_max.
+    pub fn is_valid(&self, value: &NetCDFValue) -> bool {
+        self.valid_values().is_valid(value)
+    }
+
+    /// For each value true if it is not valid, see is_valid().
+    pub fn mask(&self, values: &NetCDFArray) -> Vec<bool> {
+        let valid_values = self.valid_values();
+        (0..values.len())
+            .map(|index| !values.get(index).is_some_and(|value| valid_values.is_valid(&value)))
+            .collect()
+    }
+
+    /// Masks the data of this variable, see mask().
+    pub fn mask_data(&self, data: NetCDFVariableData) -> NetCDFMaskedData {
+        let mask = self.mask(&data.values);
+        NetCDFMaskedData{shape: data.shape, values: data.values, mask}
+    }
+
+    /// Type of the values after unpacking, as in the CF conventions: the type of
+    /// scale_factor and add_offset if they are float or double, otherwise float
+    /// for float variables and double for all other variables.
+    pub fn unpacked_type(&self) -> NetCDFType {
+        let attribute_types: Vec<NetCDFType> = ["scale_factor", "add_offset"].iter()
+            .filter_map(|name| self.attribute_values(name).first().map(|value| value.nc_type()))
+            .collect();
+
+        if attribute_types.contains(&NetCDFType::NCDouble) {
+            NetCDFType::NCDouble
+        } else if attribute_types.contains(&NetCDFType::NCFloat) || self.nc_type == NetCDFType::NCFloat {
+            NetCDFType::NCFloat
+        } else {
+            NetCDFType::NCDouble
+        }
+    }
+
+    /// Masks the data (see mask()) and applies scale_factor and add_offset:
+    /// value * scale_factor + add_offset. Masked values are NaN.
+    /// The values are of type unpacked_type().
+    pub fn unpack_data(&self, data: NetCDFVariableData) -> NetCDFMaskedData {
+        let mask = self.mask(&data.values);
+        let scale_factor = self.attribute_values("scale_factor").first().map_or(1.0, NetCDFValue::to_f64);
+        let add_offset = self.attribute_values("add_offset").first().map_or(0.0, NetCDFValue::to_f64);
+
+        let values: Vec<f64> = mask.iter().enumerate().map(|(index, masked)| {
+            match data.values.get(index) {
+                Some(value) if !masked => (value.to_f64() * scale_factor) + add_offset,
+                _ => f64::NAN,
+            }
+        }).collect();
+
+        let values = match self.unpacked_type() {
+            NetCDFType::NCFloat => NetCDFArray::Float(values.into_iter().map(|value| value as f32).collect()),
+            _ => NetCDFArray::Double(values),
+        };
+
+        NetCDFMaskedData{shape: data.shape, values, mask}
+    }
+
+    fn attribute_values(&self, name: &str) -> Vec<NetCDFValue> {
+        self.attribute(name)
+            .map_or_else(Vec::new, |attribute| (0..attribute.values.len()).filter_map(|i| attribute.values.get(i)).collect())
+    }
+
+    fn valid_values(&self) -> ValidValues {
+        let has_fill_value = self.attribute("_FillValue").is_some();
+        // Like in the netCDF users guide, the default fill value of a byte
+        // variable is not used for masking, all byte values may be valid data.
+        let byte_type = matches!(self.nc_type, NetCDFType::NCByte | NetCDFType::NCUByte | NetCDFType::NCChar);
+
+        let fill_value = if has_fill_value || !byte_type {
+            Some(self.fill_value())
+        } else {
+            None
+        };
+
+        let valid_range = self.attribute_values("valid_range");
+        let (valid_min, valid_max) = if valid_range.len() == 2 {
+            (valid_range.first().cloned(), valid_range.get(1).cloned())
+        } else {
+            (self.attribute_values("valid_min").first().cloned(), self.attribute_values("valid_max").first().cloned())
+        };
+
+        ValidValues{fill_value, missing_values: self.attribute_values("missing_value"), valid_min, valid_max}
+    }
+}
+
+fn pack_values<T: TryFrom<i128> + Copy>(packed: &[Option<i128>], fill_value: T) -> Result<Vec<T>, NetCDFError> {
+    packed.iter().map(|value| match value {
+        None => Ok(fill_value),
+        Some(value) => T::try_from(*value)
+            .map_err(|_| NetCDFError::InvalidData(format!("packed value {} is out of range", value))),
+    }).collect()
+}
+
+/// The attributes of a variable that define which values are valid.
+struct ValidValues {
+    fill_value: Option<NetCDFValue>,
+    missing_values: Vec<NetCDFValue>,
+    valid_min: Option<NetCDFValue>,
+    valid_max: Option<NetCDFValue>,
+}
+
+impl ValidValues {
+    fn is_valid(&self, value: &NetCDFValue) -> bool {
+        if let Some(fill_value) = &self.fill_value {
+            if fill_value.matches(value) {
+                return false
+            }
+        }
+
+        if self.missing_values.iter().any(|missing_value| missing_value.matches(value)) {
+            return false
+        }
+
+        if let Some(valid_min) = &self.valid_min {
+            if value.compare(valid_min) == Some(Ordering::Less) {
+                return false
+            }
+        }
+
+        if let Some(valid_max) = &self.valid_max {
+            if value.compare(valid_max) == Some(Ordering::Greater) {
+                return false
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
 pub(crate) enum NetCDFOffset {
     Pos32(u32),
     Pos64(u64),
 }
 
+impl NetCDFOffset {
+    pub(crate) fn position(&self) -> u64 {
+        match self {
+            NetCDFOffset::Pos32(offset) => *offset as u64,
+            NetCDFOffset::Pos64(offset) => *offset,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct NetCDFData {
     pub(crate) non_recs: Vec<NetCDFVarData>,
@@ -126,7 +1083,7 @@ pub(crate) struct NetCDFData {
 
 #[derive(Debug)]
 pub(crate) struct NetCDFVarData {
-    pub(crate) values: Vec<NetCDFValue>,
+    pub(crate) values: NetCDFArray,
 }
 
 #[derive(Debug)]
@@ -136,7 +1093,7 @@ pub(crate) struct NetCDFRecord {
 
 #[derive(Debug)]
 pub(crate) struct NetCDFVarSlab {
-    pub(crate) varslab: Vec<NetCDFValue>,
+    pub(crate) varslab: NetCDFArray,
 }
 
 #[derive(Debug)]
@@ -144,11 +1101,22 @@ pub enum NetCDFError {
     IOError(io::Error),
     UnknownVersion(FourBytes),
     FromUtf8(FromUtf8Error),
-    DimListTag((FourBytes, FourBytes)),
-    AttrListTag((FourBytes, FourBytes)),
+    DimListTag((FourBytes, u64)),
+    AttrListTag((FourBytes, u64)),
     NCType(FourBytes),
     HDF5NotSupportetYet,
     UnknownOffsetVersion,
+    InvalidOffset((u64, u64)),
+    InvalidDimensionId(u64),
+    UnknownVariable(String),
+    InvalidSlice(String),
+    WriteVersionNotSupported,
+    InvalidData(String),
+    InvalidDefinition(String),
+    InvalidTime(String),
+    InvalidUnit(String),
+    InvalidCdl(String),
+    InvalidConversion(String),
 }
 
 
@@ -176,10 +1144,10 @@ impl Display for NetCDFError {
                 write!(formatter, "Unknown version: {:x?}", v)
             }
             NetCDFError::DimListTag((t1, t2)) => {
-                write!(formatter, "Unknown tag in dim_list: {:x?}, {:x?}", t1, t2)
+                write!(formatter, "Unknown tag in dim_list: {:x?}, {}", t1, t2)
             }
             NetCDFError::AttrListTag((t1, t2)) => {
-                write!(formatter, "Unknown tag in attr_list: {:x?}, {:x?}", t1, t2)
+                write!(formatter, "Unknown tag in attr_list: {:x?}, {}", t1, t2)
             }
             NetCDFError::FromUtf8(e) => {
                 write!(formatter, "Could not convert to String: {}", e)
@@ -191,41 +1159,162 @@ impl Display for NetCDFError {
                 write!(formatter, "Version 4 with HDF5 is not supported yet")
             }
             NetCDFError::UnknownOffsetVersion => {
-                write!(formatter, "The offset version is not known, must be old format version 1 or 2")
+                write!(formatter, "The offset version is not known, must be old format version 1, 2 or 5")
+            }
+            NetCDFError::InvalidOffset((offset, position)) => {
+                write!(formatter, "Data offset {} lies before current file position {}", offset, position)
+            }
+            NetCDFError::InvalidDimensionId(id) => {
+                write!(formatter, "Variable refers to unknown dimension id: {}", id)
+            }
+            NetCDFError::UnknownVariable(name) => {
+                write!(formatter, "Unknown variable: '{}'", name)
+            }
+            NetCDFError::InvalidSlice(message) => {
+                write!(formatter, "Invalid slice: {}", message)
+            }
+            NetCDFError::WriteVersionNotSupported => {
+                write!(formatter, "Writing is only supported for the classic format versions 1 (CDF01), 2 (CDF02) and 5 (CDF05)")
+            }
+            NetCDFError::InvalidData(message) => {
+                write!(formatter, "Invalid data: {}", message)
+            }
+            NetCDFError::InvalidDefinition(message) => {
+                write!(formatter, "Invalid definition: {}", message)
+            }
+            NetCDFError::InvalidTime(message) => {
+                write!(formatter, "Invalid time: {}", message)
+            }
+            NetCDFError::InvalidUnit(message) => {
+                write!(formatter, "Invalid unit: {}", message)
+            }
+            NetCDFError::InvalidCdl(message) => {
+                write!(formatter, "Invalid CDL: {}", message)
+            }
+            NetCDFError::InvalidConversion(message) => {
+                write!(formatter, "Invalid conversion: {}", message)
             }
         }
     }
 }
 
 impl NetCDF {
-    pub fn num_of_records(&self) -> u32 {
-        match self.header.numrecs {
-            NetCDFStreaming::Streaming => 0,
-            NetCDFStreaming::Normal(n) => n,
-        }
+    pub fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    pub fn num_of_records(&self) -> u64 {
+        self.header.num_of_records()
     }
 
     pub fn num_of_dimensions(&self) -> u32 {
-        self.header.dim_list.len() as u32
+        self.header.num_of_dimensions()
     }
 
     pub fn num_of_attributes(&self) -> u32 {
-        self.header.att_list.len() as u32
+        self.header.num_of_attributes()
     }
 
     pub fn num_of_variables(&self) -> u32 {
-        self.header.var_list.len() as u32
+        self.header.num_of_variables()
     }
 
     pub fn list_of_dimensions(&self) -> &[NetCDFDimension] {
-        self.header.dim_list.as_slice()
+        self.header.list_of_dimensions()
     }
 
     pub fn list_of_attributes(&self) -> &[NetCDFAttribute] {
-        self.header.att_list.as_slice()
+        self.header.list_of_attributes()
     }
 
     pub fn list_of_variables(&self) -> &[NetCDFVariable] {
-        self.header.var_list.as_slice()
+        self.header.list_of_variables()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&NetCDFVariable> {
+        self.header.variable(name)
+    }
+
+    pub fn dimension(&self, name: &str) -> Option<&NetCDFDimension> {
+        self.header.dimension(name)
+    }
+
+    pub fn global_attribute(&self, name: &str) -> Option<&NetCDFAttribute> {
+        self.header.global_attribute(name)
+    }
+
+    pub fn unlimited_dimension(&self) -> Option<&NetCDFDimension> {
+        self.header.unlimited_dimension()
+    }
+
+    pub fn variable_dimensions(&self, variable: &NetCDFVariable) -> Vec<&NetCDFDimension> {
+        self.header.variable_dimensions(variable)
+    }
+
+    pub fn shape(&self, variable: &NetCDFVariable) -> Vec<usize> {
+        self.header.shape(variable)
+    }
+
+    pub fn is_record_variable(&self, variable: &NetCDFVariable) -> bool {
+        self.header.is_record_variable(variable)
+    }
+
+    pub fn variable_data(&self, name: &str) -> Result<NetCDFVariableData, NetCDFError> {
+        let (index, variable) = self.header.find_variable(name)?;
+        let is_record = self.header.is_record_variable(variable);
+        let shape = self.header.variable_shape(variable, self.data.recs.len());
+
+        // Position of the variable within the non-record or record variables
+        let data_index = self.header.var_list[..index].iter()
+            .filter(|other| self.header.is_record_variable(other) == is_record)
+            .count();
+
+        let values = if is_record {
+            let num_of_values = self.header.num_of_values(variable) * self.data.recs.len();
+            let mut values = NetCDFArray::with_capacity(variable.nc_type, num_of_values);
+
+            for record in self.data.recs.iter() {
+                values.extend_from(&record.record[data_index].varslab);
+            }
+
+            values
+        } else {
+            self.data.non_recs[data_index].values.clone()
+        };
+
+        Ok(NetCDFVariableData{shape, values})
+    }
+}
+
+/// Masked and unpacked reading on top of variable_data(), implemented for &NetCDF,
+/// &mut NetCDFReader and &NetCDFMmap, so these methods can be called on all of them.
+pub trait NetCDFVariableSource: Sized {
+    fn header(&self) -> &NetCDFHeader;
+
+    fn variable_data(&mut self, name: &str) -> Result<NetCDFVariableData, NetCDFError>;
+
+    /// Like variable_data(), with fill values, missing values and values
+    /// outside of the valid range masked out.
+    fn variable_data_masked(mut self, name: &str) -> Result<NetCDFMaskedData, NetCDFError> {
+        let data = self.variable_data(name)?;
+        let (_, variable) = self.header().find_variable(name)?;
+        Ok(variable.mask_data(data))
+    }
+
+    /// Like variable_data_masked(), with scale_factor and add_offset applied.
+    fn variable_data_unpacked(mut self, name: &str) -> Result<NetCDFMaskedData, NetCDFError> {
+        let data = self.variable_data(name)?;
+        let (_, variable) = self.header().find_variable(name)?;
+        Ok(variable.unpack_data(data))
+    }
+}
+
+impl NetCDFVariableSource for &NetCDF {
+    fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    fn variable_data(&mut self, name: &str) -> Result<NetCDFVariableData, NetCDFError> {
+        NetCDF::variable_data(self, name)
     }
 }
@@ -3,7 +3,7 @@
 // Rust modules
 use std::path::Path;
 use std::fs::File;
-use std::{io::BufReader, io::Read};
+use std::{io, io::BufReader, io::Read, io::Seek, io::SeekFrom};
 // use std::{fmt, fmt::Display, fmt::Formatter};
 // use std::string::FromUtf8Error;
 
@@ -14,36 +14,211 @@ use byteorder::{ByteOrder, BigEndian};
 // Internal modules
 use crate::netcdf::*;
 
+/// Largest buffer that is allocated in advance for the values of a variable.
+const MAX_INITIAL_CAPACITY: usize = 1 << 20;
 
 pub fn load_file<T: AsRef<Path>>(path: T) -> Result<NetCDF, NetCDFError> {
     let file_path = path.as_ref();
     info!("reader.rs, load_file, trying to open file: '{}'", file_path.display());
     let file = File::open(file_path)?;
+    let file_size = file.metadata()?.len();
     let mut buf_reader = BufReader::new(file);
-    load_reader(&mut buf_reader)
+    read_netcdf(&mut buf_reader, Some(file_size))
 }
 
 pub fn load_reader<T: Read>(reader: &mut T) -> Result<NetCDF, NetCDFError> {
-    let header = read_header(reader)?;
-    let data = read_data(reader, &header)?;
+    read_netcdf(reader, None)
+}
+
+/// Without the size of the file the records are only checked while they are read.
+fn read_netcdf<T: Read>(reader: &mut T, file_size: Option<u64>) -> Result<NetCDF, NetCDFError> {
+    let mut reader = PositionReader{reader, position: 0};
+    let mut header = read_header(&mut reader)?;
+
+    if let (NetCDFStreaming::Normal(_), Some(file_size)) = (&header.numrecs, file_size) {
+        header.check_num_of_records(file_size)?;
+    }
+
+    let data = read_data(&mut reader, &header)?;
+
+    if let NetCDFStreaming::Streaming(_) = header.numrecs {
+        header.numrecs = NetCDFStreaming::Streaming(data.recs.len() as u64);
+    }
 
     Ok(NetCDF{header, data})
 }
 
-fn read_header<T: Read>(reader: &mut T) -> Result<NetCDFHeader, NetCDFError> {
+/// Reads only the format version of a file, this works for netCDF-4 (HDF5) files, too.
+pub fn file_version<T: AsRef<Path>>(path: T) -> Result<NetCDFVersion, NetCDFError> {
+    let mut file = File::open(path)?;
+    read_version(&mut file)
+}
+
+/// Opens a file and parses only the header,
+/// variable data is read on demand via NetCDFReader.
+pub fn open_file<T: AsRef<Path>>(path: T) -> Result<NetCDFReader<BufReader<File>>, NetCDFError> {
+    let file_path = path.as_ref();
+    info!("reader.rs, open_file, trying to open file: '{}'", file_path.display());
+    let file = File::open(file_path)?;
+    open_reader(BufReader::new(file))
+}
+
+pub fn open_reader<R: Read + Seek>(mut reader: R) -> Result<NetCDFReader<R>, NetCDFError> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut header = read_header(&mut reader)?;
+    let file_size = reader.seek(SeekFrom::End(0))?;
+    header.resolve_streaming(file_size);
+    header.check_num_of_records(file_size)?;
+
+    Ok(NetCDFReader{header, reader})
+}
+
+/// Lazy access to a netCDF file: the header is kept in memory and
+/// the data of a variable is read when it is requested.
+pub struct NetCDFReader<R: Read + Seek> {
+    header: NetCDFHeader,
+    reader: R,
+}
+
+impl<R: Read + Seek> NetCDFReader<R> {
+    pub fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    pub fn variable_data(&mut self, name: &str) -> Result<NetCDFVariableData, NetCDFError> {
+        let (_, variable) = self.header.find_variable(name)?;
+        let num_of_records = self.header.num_of_records() as usize;
+        let shape = self.header.variable_shape(variable, num_of_records);
+        let nvals = self.header.num_of_values(variable);
+        let offset = variable.offset.position();
+        debug!("NetCDFReader::variable_data, name: '{}', offset: {}", name, offset);
+
+        let values = if self.header.is_record_variable(variable) {
+            let record_size = self.header.record_size();
+            let mut values = NetCDFArray::with_capacity(variable.nc_type,
+                nvals.saturating_mul(num_of_records).min(MAX_INITIAL_CAPACITY));
+
+            for record_index in 0..num_of_records as u64 {
+                self.reader.seek(SeekFrom::Start(offset + (record_index * record_size)))?;
+                values.extend_from(&read_values_unpadded(&mut self.reader, variable.nc_type, nvals)?);
+            }
+
+            values
+        } else {
+            self.reader.seek(SeekFrom::Start(offset))?;
+            read_values_unpadded(&mut self.reader, variable.nc_type, nvals)?
+        };
+
+        Ok(NetCDFVariableData{shape, values})
+    }
+
+    /// Reads a hyperslab of a variable, like nc_get_vars in netCDF-C.
+    /// If no stride is given, all strides are 1.
+    pub fn read_slice(&mut self, name: &str, start: &[usize], count: &[usize], stride: Option<&[usize]>)
+        -> Result<NetCDFVariableData, NetCDFError> {
+        let NetCDFReader{header, reader} = self;
+
+        read_slice_runs(header, name, start, count, stride, |nc_type, offset, span| {
+            reader.seek(SeekFrom::Start(offset))?;
+            read_values_unpadded(reader, nc_type, span)
+        })
+    }
+}
+
+impl<R: Read + Seek> NetCDFVariableSource for &mut NetCDFReader<R> {
+    fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    fn variable_data(&mut self, name: &str) -> Result<NetCDFVariableData, NetCDFError> {
+        NetCDFReader::variable_data(self, name)
+    }
+}
+
+/// Reads a hyperslab with the parts given by NetCDFHeader::slice_runs(). read_run returns
+/// span values that follow each other in the file, starting at the given offset.
+pub(crate) fn read_slice_runs<F>(header: &NetCDFHeader, name: &str, start: &[usize], count: &[usize], stride: Option<&[usize]>,
+        mut read_run: F) -> Result<NetCDFVariableData, NetCDFError>
+        where F: FnMut(NetCDFType, u64, usize) -> Result<NetCDFArray, NetCDFError> {
+    let (_, variable) = header.find_variable(name)?;
+    let stride = stride.map_or_else(|| vec![1; count.len()], |stride| stride.to_vec());
+    let runs = header.slice_runs(variable, start, count, &stride)?;
+    let step = stride.last().cloned().unwrap_or(1);
+    debug!("read_slice_runs, name: '{}', number of runs: {}", name, runs.len());
+
+    let num_of_values: usize = count.iter().product();
+    let mut values = NetCDFArray::with_capacity(variable.nc_type, num_of_values.min(MAX_INITIAL_CAPACITY));
+
+    for (offset, run_length) in runs {
+        let span = ((run_length - 1) * step) + 1;
+        let run = read_run(variable.nc_type, offset, span)?;
+
+        if step == 1 {
+            values.extend_from(&run);
+        } else {
+            values.extend_from(&run.step_by(step));
+        }
+    }
+
+    Ok(NetCDFVariableData{shape: count.to_vec(), values})
+}
+
+/// Keeps track of the number of bytes read so far, since the data section
+/// can only be located via the offsets stored in the header.
+struct PositionReader<'a, T: Read> {
+    reader: &'a mut T,
+    position: u64,
+}
+
+impl<'a, T: Read> Read for PositionReader<'a, T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let num_of_bytes = self.reader.read(buf)?;
+        self.position += num_of_bytes as u64;
+        Ok(num_of_bytes)
+    }
+}
+
+impl<'a, T: Read> PositionReader<'a, T> {
+    fn skip_to(&mut self, offset: u64) -> Result<(), NetCDFError> {
+        if offset < self.position {
+            return Err(NetCDFError::InvalidOffset((offset, self.position)))
+        }
+
+        let num_of_bytes = offset - self.position;
+        debug!("skip_to, offset: {}, skipping: {}", offset, num_of_bytes);
+        let skipped = io::copy(&mut self.by_ref().take(num_of_bytes), &mut io::sink())?;
+
+        if skipped < num_of_bytes {
+            return Err(NetCDFError::IOError(io::Error::new(io::ErrorKind::UnexpectedEof,
+                "file ends before data offset")))
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn read_header<T: Read>(reader: &mut T) -> Result<NetCDFHeader, NetCDFError> {
     let version = read_version(reader)?;
     info!("NetCDF version: {:?}", version);
 
     match version {
         NetCDFVersion::HDF5 => Err(NetCDFError::HDF5NotSupportetYet),
         _ => {
-            let numrecs = read_numrecs(reader)?;
+            let numrecs = read_numrecs(reader, &version)?;
             info!("NetCDF number of records: {:?}", numrecs);
 
-            let dim_list = read_dim_list(reader)?;
-            let att_list = read_att_list(reader)?;
+            let dim_list = read_dim_list(reader, &version)?;
+            let att_list = read_att_list(reader, &version)?;
             let var_list = read_var_list(reader, &version)?;
 
+            for variable in var_list.iter() {
+                for dim_id in variable.dimid.iter() {
+                    if *dim_id as usize >= dim_list.len() {
+                        return Err(NetCDFError::InvalidDimensionId(*dim_id as u64))
+                    }
+                }
+            }
+
             Ok(NetCDFHeader{version, numrecs, dim_list, att_list, var_list})
         }
     }
@@ -57,111 +232,114 @@ fn read_version<T: Read>(reader: &mut T) -> Result<NetCDFVersion, NetCDFError> {
     match buffer {
         VERSION1 => Ok(NetCDFVersion::CDF01),
         VERSION2 => Ok(NetCDFVersion::CDF02),
+        VERSION5 => Ok(NetCDFVersion::CDF05),
         VERSION4 => Ok(NetCDFVersion::HDF5),
         _ => Err(NetCDFError::UnknownVersion(buffer))
     }
 }
 
-fn read_numrecs<T: Read>(reader: &mut T) -> Result<NetCDFStreaming, NetCDFError> {
-    let mut buffer: FourBytes = [0; 4];
-    reader.read_exact(&mut buffer)?;
-    debug!("Numrecs buffer: {:?}", buffer);
+fn read_numrecs<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<NetCDFStreaming, NetCDFError> {
+    match version {
+        NetCDFVersion::CDF05 => {
+            let mut buffer: EightBytes = [0; 8];
+            reader.read_exact(&mut buffer)?;
+            debug!("Numrecs buffer: {:?}", buffer);
 
-    match buffer {
-        STREAMING => Ok(NetCDFStreaming::Streaming),
+            match buffer {
+                STREAMING64 => Ok(NetCDFStreaming::Streaming(0)),
+                _ => Ok(NetCDFStreaming::Normal(u64::from_be_bytes(buffer))),
+            }
+        }
         _ => {
-            let value1 = u32::from_be_bytes(buffer);
-            debug!("Numrecs BE: {}", value1);
+            let mut buffer: FourBytes = [0; 4];
+            reader.read_exact(&mut buffer)?;
+            debug!("Numrecs buffer: {:?}", buffer);
+
+            match buffer {
+                STREAMING => Ok(NetCDFStreaming::Streaming(0)),
+                _ => {
+                    let value1 = u32::from_be_bytes(buffer);
+                    debug!("Numrecs BE: {}", value1);
 
-            // let value2 = u32::from_le_bytes(buffer);
-            // debug!("Numrecs LE: {}", value2);
+                    // let value2 = u32::from_le_bytes(buffer);
+                    // debug!("Numrecs LE: {}", value2);
 
-            // Big Endian is correct
-            Ok(NetCDFStreaming::Normal(value1))
+                    // Big Endian is correct
+                    Ok(NetCDFStreaming::Normal(value1 as u64))
+                }
+            }
         }
     }
 }
 
-fn read_dim_list<T: Read>(reader: &mut T) -> Result<Vec<NetCDFDimension>, NetCDFError> {
+fn read_dim_list<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<Vec<NetCDFDimension>, NetCDFError> {
     let mut result = Vec::new();
-    let mut buffer1: FourBytes = [0; 4];
-    let mut buffer2: FourBytes = [0; 4];
-    reader.read_exact(&mut buffer1)?;
-    reader.read_exact(&mut buffer2)?;
-    debug!("Dimlist buffer1: {:?}", buffer1);
-    debug!("Dimlist buffer2: {:?}", buffer2);
-
-    match (buffer1, buffer2) {
-        (ZERO, ZERO) => {
+    let mut buffer: FourBytes = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    let nelem = read_number_of_elements(reader, version)?;
+    debug!("Dimlist buffer: {:?}", buffer);
+    debug!("Dimlist nelem: {}", nelem);
+
+    match (buffer, nelem) {
+        (ZERO, 0) => {
             // No dimensions given, return empty vector
             Ok(result)
         }
         (NC_DIMENSION, _) => {
-            let nelem = u32::from_be_bytes(buffer2);
-            debug!("Nelems dimlist BE: {}", nelem);
-
             for _ in 0..nelem {
-                let dimension = read_dimension(reader)?;
+                let dimension = read_dimension(reader, version)?;
                 result.push(dimension);
             }
 
             Ok(result)
         }
         _ => {
-            Err(NetCDFError::DimListTag((buffer1, buffer2)))
+            Err(NetCDFError::DimListTag((buffer, nelem)))
         }
     }
 }
 
-fn read_att_list<T: Read>(reader: &mut T) -> Result<Vec<NetCDFAttribute>, NetCDFError> {
+fn read_att_list<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<Vec<NetCDFAttribute>, NetCDFError> {
     let mut result = Vec::new();
-    let mut buffer1: FourBytes = [0; 4];
-    let mut buffer2: FourBytes = [0; 4];
-    reader.read_exact(&mut buffer1)?;
-    reader.read_exact(&mut buffer2)?;
-    debug!("Attlist buffer1: {:?}", buffer1);
-    debug!("Attlist buffer2: {:?}", buffer2);
-
-    match (buffer1, buffer2) {
-        (ZERO, ZERO) => {
+    let mut buffer: FourBytes = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    let nelem = read_number_of_elements(reader, version)?;
+    debug!("Attlist buffer: {:?}", buffer);
+    debug!("Attlist nelem: {}", nelem);
+
+    match (buffer, nelem) {
+        (ZERO, 0) => {
             // No attributes given, return empty vector
             Ok(result)
         }
         (NC_ATTRIBUTE, _) => {
-            let nelem = u32::from_be_bytes(buffer2);
-            debug!("Nelems attlist BE: {}", nelem);
-
             for _ in 0..nelem {
-                let attribute = read_attribute(reader)?;
+                let attribute = read_attribute(reader, version)?;
                 result.push(attribute);
             }
 
             Ok(result)
         }
         _ => {
-            Err(NetCDFError::AttrListTag((buffer1, buffer2)))
+            Err(NetCDFError::AttrListTag((buffer, nelem)))
         }
     }
 }
 
 fn read_var_list<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<Vec<NetCDFVariable>, NetCDFError> {
     let mut result = Vec::new();
-    let mut buffer1: FourBytes = [0; 4];
-    let mut buffer2: FourBytes = [0; 4];
-    reader.read_exact(&mut buffer1)?;
-    reader.read_exact(&mut buffer2)?;
-    debug!("Varlist buffer1: {:?}", buffer1);
-    debug!("Varlist buffer2: {:?}", buffer2);
-
-    match (buffer1, buffer2) {
-        (ZERO, ZERO) => {
+    let mut buffer: FourBytes = [0; 4];
+    reader.read_exact(&mut buffer)?;
+    let nelem = read_number_of_elements(reader, version)?;
+    debug!("Varlist buffer: {:?}", buffer);
+    debug!("Varlist nelem: {}", nelem);
+
+    match (buffer, nelem) {
+        (ZERO, 0) => {
             // No attributes given, return empty vector
             Ok(result)
         }
         (NC_VARIABLE, _) => {
-            let nelem = u32::from_be_bytes(buffer2);
-            debug!("Nelems varlist BE: {}", nelem);
-
             for _ in 0..nelem {
                 let attribute = read_variable(reader, version)?;
                 result.push(attribute);
@@ -170,27 +348,27 @@ fn read_var_list<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<Vec
             Ok(result)
         }
         _ => {
-            Err(NetCDFError::AttrListTag((buffer1, buffer2)))
+            Err(NetCDFError::AttrListTag((buffer, nelem)))
         }
     }
 }
 
-fn read_data<T: Read>(reader: &mut T, header: &NetCDFHeader) -> Result<NetCDFData, NetCDFError> {
-    let non_recs = read_non_records(reader)?;
-    let recs = read_records(reader)?;
+fn read_data<T: Read>(reader: &mut PositionReader<T>, header: &NetCDFHeader) -> Result<NetCDFData, NetCDFError> {
+    let non_recs = read_non_records(reader, header)?;
+    let recs = read_records(reader, header)?;
 
     Ok(NetCDFData{non_recs, recs})
 }
 
-fn read_name<T: Read>(reader: &mut T) -> Result<String, NetCDFError> {
+fn read_name<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<String, NetCDFError> {
     let mut buffer1: OneByte = [0; 1];
 
-    let name_length = read_number_of_elements(reader)?;
+    let name_length = read_number_of_elements(reader, version)?;
     debug!("read_name length: {}", name_length);
 
     let reader2 = reader.by_ref();
     let mut buffer2 = Vec::new();
-    reader2.take(name_length as u64).read_to_end(&mut buffer2)?;
+    reader2.take(name_length).read_to_end(&mut buffer2)?;
     debug!("read_name buffer2: {:?}", buffer2);
 
     let padding = 4 - (name_length % 4);
@@ -202,13 +380,23 @@ fn read_name<T: Read>(reader: &mut T) -> Result<String, NetCDFError> {
         }
     }
 
-    String::from_utf8(buffer2).map_err(|e| NetCDFError::FromUtf8(e))
+    String::from_utf8(buffer2).map_err(NetCDFError::FromUtf8)
 }
 
-fn read_number_of_elements<T: Read>(reader: &mut T) -> Result<u32, NetCDFError> {
-    let mut buffer: FourBytes = [0; 4];
-    reader.read_exact(&mut buffer)?;
-    Ok(u32::from_be_bytes(buffer))
+/// Non-negative numbers are 32 bit in CDF-1 and CDF-2 and 64 bit in CDF-5.
+fn read_number_of_elements<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<u64, NetCDFError> {
+    match version {
+        NetCDFVersion::CDF05 => {
+            let mut buffer: EightBytes = [0; 8];
+            reader.read_exact(&mut buffer)?;
+            Ok(u64::from_be_bytes(buffer))
+        }
+        _ => {
+            let mut buffer: FourBytes = [0; 4];
+            reader.read_exact(&mut buffer)?;
+            Ok(u32::from_be_bytes(buffer) as u64)
+        }
+    }
 }
 
 fn read_nc_type<T: Read>(reader: &mut T) -> Result<NetCDFType, NetCDFError> {
@@ -223,144 +411,158 @@ fn read_nc_type<T: Read>(reader: &mut T) -> Result<NetCDFType, NetCDFError> {
         NC_INT => Ok(NetCDFType::NCInt),
         NC_FLOAT => Ok(NetCDFType::NCFloat),
         NC_DOUBLE => Ok(NetCDFType::NCDouble),
+        NC_UBYTE => Ok(NetCDFType::NCUByte),
+        NC_USHORT => Ok(NetCDFType::NCUShort),
+        NC_UINT => Ok(NetCDFType::NCUInt),
+        NC_INT64 => Ok(NetCDFType::NCInt64),
+        NC_UINT64 => Ok(NetCDFType::NCUInt64),
         _ => Err(NetCDFError::NCType(buffer))
     }
 }
 
-fn read_values<T: Read>(reader: &mut T, nc_type: NetCDFType, nvals: u32) -> Result<Vec<NetCDFValue>, NetCDFError> {
-    let mut result = Vec::new();
+fn read_values<T: Read>(reader: &mut T, nc_type: NetCDFType, nvals: usize) -> Result<NetCDFArray, NetCDFError> {
+    let result = read_values_unpadded(reader, nc_type, nvals)?;
 
-    match nc_type {
-        NetCDFType::NCByte => {
-            let size_in_bytes = nvals;
-            let padding = 4 - (size_in_bytes % 4);
-            debug!("read_values, byte, padding: {}", padding);
+    let padding = padding_size(nc_type, nvals);
+    debug!("read_values, {:?}, padding: {}", nc_type, padding);
 
-            let mut buffer: OneByte = [0; 1];
+    let mut buffer: OneByte = [0; 1];
 
-            for _ in 0..nvals {
-                reader.read_exact(&mut buffer)?;
-                result.push(NetCDFValue::Byte(buffer[0]))
-            }
+    for _ in 0..padding {
+        // Ignore padding fill bytes
+        reader.read_exact(&mut buffer)?;
+    }
 
-            if padding < 4 {
-                for _ in 0..padding {
-                    // Ignore padding fill bytes
-                    reader.read_exact(&mut buffer)?;
-                }
-            }
-        }
-        NetCDFType::NCChar => {
-            let size_in_bytes = nvals;
-            let padding = 4 - (size_in_bytes % 4);
-            debug!("read_values, char, padding: {}", padding);
+    Ok(result)
+}
 
-            let mut buffer: OneByte = [0; 1];
+/// The number of values comes from the header, so the buffer only grows
+/// while the data is read: a corrupt header can not allocate more memory
+/// than there is data in the file.
+fn read_values_unpadded<T: Read>(reader: &mut T, nc_type: NetCDFType, nvals: usize) -> Result<NetCDFArray, NetCDFError> {
+    let num_of_bytes = nvals.checked_mul(nc_type.size_in_bytes())
+        .ok_or_else(|| NetCDFError::InvalidData(format!("{} values of type {:?} do not fit into memory", nvals, nc_type)))?;
+    let mut buffer = Vec::with_capacity(num_of_bytes.min(MAX_INITIAL_CAPACITY));
+    reader.by_ref().take(num_of_bytes as u64).read_to_end(&mut buffer)?;
+
+    if buffer.len() < num_of_bytes {
+        return Err(NetCDFError::IOError(io::Error::new(io::ErrorKind::UnexpectedEof,
+            "file ends before the end of the variable data")))
+    }
 
-            for _ in 0..nvals {
-                reader.read_exact(&mut buffer)?;
-                result.push(NetCDFValue::Char(buffer[0] as char))
-            }
+    Ok(decode_values(&buffer, nc_type))
+}
 
-            if padding < 4 {
-                for _ in 0..padding {
-                    // Ignore padding fill bytes
-                    reader.read_exact(&mut buffer)?;
-                }
-            }
+/// Converts the big endian bytes of the file into typed values.
+pub(crate) fn decode_values(buffer: &[u8], nc_type: NetCDFType) -> NetCDFArray {
+    let nvals = buffer.len() / nc_type.size_in_bytes();
+
+    match nc_type {
+        NetCDFType::NCByte => {
+            NetCDFArray::Byte(buffer.iter().map(|b| *b as i8).collect())
+        }
+        NetCDFType::NCChar => {
+            NetCDFArray::Char(buffer.to_vec())
         }
         NetCDFType::NCShort => {
-            let size_in_bytes = nvals * 2;
-            let padding = 4 - (size_in_bytes % 4);
-            debug!("read_values, short, padding: {}", padding);
-
-            let mut buffer: TwoBytes = [0; 2];
-
-            for _ in 0..nvals {
-                reader.read_exact(&mut buffer)?;
-                result.push(NetCDFValue::Short(i16::from_be_bytes(buffer)))
-            }
-
-            if padding == 2 {
-                // Ignore padding fill bytes
-                // Padding can only be 0 or 2
-                // and if it is 2 ready exactly 2 bytes.
-                // Buffer size is also 2
-                reader.read_exact(&mut buffer)?;
-            }
+            let mut result = vec![0; nvals];
+            BigEndian::read_i16_into(buffer, &mut result);
+            NetCDFArray::Short(result)
         }
         NetCDFType::NCInt => {
-            let mut buffer: FourBytes = [0; 4];
-
-            for _ in 0..nvals {
-                reader.read_exact(&mut buffer)?;
-                result.push(NetCDFValue::Int(i32::from_be_bytes(buffer)))
-            }
+            let mut result = vec![0; nvals];
+            BigEndian::read_i32_into(buffer, &mut result);
+            NetCDFArray::Int(result)
         }
         NetCDFType::NCFloat => {
-            let mut buffer: FourBytes = [0; 4];
-
-            for _ in 0..nvals {
-                reader.read_exact(&mut buffer)?;
-                result.push(NetCDFValue::Float(BigEndian::read_f32(&buffer)))
-            }
+            let mut result = vec![0.0; nvals];
+            BigEndian::read_f32_into(buffer, &mut result);
+            NetCDFArray::Float(result)
         }
         NetCDFType::NCDouble => {
-            let mut buffer: EightBytes = [0; 8];
-
-            for _ in 0..nvals {
-                reader.read_exact(&mut buffer)?;
-                result.push(NetCDFValue::Double(BigEndian::read_f64(&buffer)))
-            }
+            let mut result = vec![0.0; nvals];
+            BigEndian::read_f64_into(buffer, &mut result);
+            NetCDFArray::Double(result)
+        }
+        NetCDFType::NCUByte => {
+            NetCDFArray::UByte(buffer.to_vec())
+        }
+        NetCDFType::NCUShort => {
+            let mut result = vec![0; nvals];
+            BigEndian::read_u16_into(buffer, &mut result);
+            NetCDFArray::UShort(result)
+        }
+        NetCDFType::NCUInt => {
+            let mut result = vec![0; nvals];
+            BigEndian::read_u32_into(buffer, &mut result);
+            NetCDFArray::UInt(result)
+        }
+        NetCDFType::NCInt64 => {
+            let mut result = vec![0; nvals];
+            BigEndian::read_i64_into(buffer, &mut result);
+            NetCDFArray::Int64(result)
+        }
+        NetCDFType::NCUInt64 => {
+            let mut result = vec![0; nvals];
+            BigEndian::read_u64_into(buffer, &mut result);
+            NetCDFArray::UInt64(result)
         }
     }
+}
 
-    Ok(result)
+/// Values are padded with fill bytes to the next 4 byte boundary,
+/// which only affects bytes, chars and shorts.
+fn padding_size(nc_type: NetCDFType, nvals: usize) -> usize {
+    let size_in_bytes = nvals * nc_type.size_in_bytes();
+    (4 - (size_in_bytes % 4)) % 4
 }
 
-fn read_dimension<T: Read>(reader: &mut T) -> Result<NetCDFDimension, NetCDFError> {
-    let name = read_name(reader)?;
+fn read_dimension<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<NetCDFDimension, NetCDFError> {
+    let name = read_name(reader, version)?;
     debug!("read_dimension, name: '{}'", name);
-    let length = read_number_of_elements(reader)?;
+    let length = read_number_of_elements(reader, version)?;
     debug!("read_dimension, length: {}", length);
     Ok(NetCDFDimension{name, length})
 }
 
-fn read_attribute<T: Read>(reader: &mut T) -> Result<NetCDFAttribute, NetCDFError> {
-    let name = read_name(reader)?;
+fn read_attribute<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<NetCDFAttribute, NetCDFError> {
+    let name = read_name(reader, version)?;
     debug!("read_attribute, name: '{}'", name);
     let nc_type = read_nc_type(reader)?;
     debug!("read_attribute, nc_type: {:?}", nc_type);
-    let nvals = read_number_of_elements(reader)?;
+    let nvals = read_number_of_elements(reader, version)?;
     debug!("read_attribute, nvals: {}", nvals);
-    let values = read_values(reader, nc_type, nvals)?;
+    let values = read_values(reader, nc_type, nvals as usize)?;
     Ok(NetCDFAttribute{name, values})
 }
 
 fn read_variable<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<NetCDFVariable, NetCDFError> {
-    let name = read_name(reader)?;
+    let name = read_name(reader, version)?;
     debug!("read_variable, name: '{}'", name);
-    let dimid = read_dimension_ids(reader)?;
-    let att_list = read_att_list(reader)?;
+    let dimid = read_dimension_ids(reader, version)?;
+    let att_list = read_att_list(reader, version)?;
     let nc_type = read_nc_type(reader)?;
     debug!("read_variable, nc_type: {:?}", nc_type);
-    let vsize = read_number_of_elements(reader)?;
+    let vsize = read_number_of_elements(reader, version)?;
     debug!("read_variable, vsize: {}", vsize);
     let offset = read_offset(reader, version)?;
     debug!("read_variable, offset: {:?}", offset);
     Ok(NetCDFVariable{name, dimid, att_list, nc_type, vsize, offset})
 }
 
-fn read_dimension_ids<T: Read>(reader: &mut T) -> Result<Vec<u32>, NetCDFError> {
+fn read_dimension_ids<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<Vec<u32>, NetCDFError> {
     let mut result = Vec::new();
-    let mut buffer: FourBytes = [0; 4];
-    let nelems = read_number_of_elements(reader)?;
+    let nelems = read_number_of_elements(reader, version)?;
     debug!("read_dimension_ids, nelems: {}", nelems);
 
     for _ in 0..nelems {
-        reader.read_exact(&mut buffer)?;
-        let dim_id = u32::from_be_bytes(buffer);
-        result.push(dim_id);
+        let dim_id = read_number_of_elements(reader, version)?;
+
+        if dim_id > u32::MAX as u64 {
+            return Err(NetCDFError::InvalidDimensionId(dim_id))
+        }
+
+        result.push(dim_id as u32);
     }
 
     Ok(result)
@@ -374,7 +576,7 @@ fn read_offset<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<NetCD
             let offset = u32::from_be_bytes(buffer);
             Ok(NetCDFOffset::Pos32(offset))
         }
-        NetCDFVersion::CDF02 => {
+        NetCDFVersion::CDF02 | NetCDFVersion::CDF05 => {
             let mut buffer: EightBytes = [0; 8];
             reader.read_exact(&mut buffer)?;
             let offset = u64::from_be_bytes(buffer);
@@ -384,12 +586,87 @@ fn read_offset<T: Read>(reader: &mut T, version: &NetCDFVersion) -> Result<NetCD
     }
 }
 
-fn read_non_records<T: Read>(reader: &mut T) -> Result<Vec<NetCDFVarData>, NetCDFError> {
-    let result = Vec::new();
-    Ok(result)
+fn read_non_records<T: Read>(reader: &mut PositionReader<T>, header: &NetCDFHeader) -> Result<Vec<NetCDFVarData>, NetCDFError> {
+    // The data section is read sequentially, so visit the variables in the order
+    // of their offsets and restore the order of the var_list afterwards.
+    let mut variables: Vec<(usize, &NetCDFVariable)> = header.var_list.iter()
+        .filter(|variable| !header.is_record_variable(variable))
+        .enumerate()
+        .collect();
+    variables.sort_by_key(|(_, variable)| variable.offset.position());
+
+    let mut result = Vec::new();
+
+    for (index, variable) in variables {
+        reader.skip_to(variable.offset.position())?;
+        let nvals = header.num_of_values(variable);
+        debug!("read_non_records, name: '{}', nvals: {}, vsize: {}", variable.name, nvals, variable.vsize);
+        let values = read_values(reader, variable.nc_type, nvals)?;
+        result.push((index, NetCDFVarData{values}));
+    }
+
+    result.sort_by_key(|(index, _)| *index);
+
+    Ok(result.into_iter().map(|(_, var_data)| var_data).collect())
+}
+
+fn read_records<T: Read>(reader: &mut PositionReader<T>, header: &NetCDFHeader) -> Result<Vec<NetCDFRecord>, NetCDFError> {
+    let record_size = header.record_size();
+
+    match (&header.numrecs, header.record_begin()) {
+        (NetCDFStreaming::Normal(num_of_records), _) => {
+            read_record_list(reader, header, *num_of_records)
+        }
+        (NetCDFStreaming::Streaming(_), Some(record_begin)) if record_size > 0 => {
+            // The number of records is only known at the end of the file
+            reader.skip_to(record_begin)?;
+            let mut buffer = Vec::new();
+            reader.read_to_end(&mut buffer)?;
+            let num_of_records = buffer.len() as u64 / record_size;
+            debug!("read_records, streaming, size of record section: {}", buffer.len());
+
+            let mut buffer_reader = PositionReader{reader: &mut buffer.as_slice(), position: record_begin};
+            read_record_list(&mut buffer_reader, header, num_of_records)
+        }
+        _ => Ok(Vec::new()),
+    }
 }
 
-fn read_records<T: Read>(reader: &mut T) -> Result<Vec<NetCDFRecord>, NetCDFError> {
-    let result = Vec::new();
+fn read_record_list<T: Read>(reader: &mut PositionReader<T>, header: &NetCDFHeader, num_of_records: u64) -> Result<Vec<NetCDFRecord>, NetCDFError> {
+    let mut variables: Vec<(usize, &NetCDFVariable)> = header.var_list.iter()
+        .filter(|variable| header.is_record_variable(variable))
+        .enumerate()
+        .collect();
+    variables.sort_by_key(|(_, variable)| variable.offset.position());
+
+    // Without record variables numrecs does not matter
+    if variables.is_empty() {
+        return Ok(Vec::new())
+    }
+
+    let record_size = header.record_size();
+    let padding = header.pads_records();
+    debug!("read_records, numrecs: {}, recsize: {}, padding: {}", num_of_records, record_size, padding);
+
+    let mut result = Vec::new();
+
+    for record_index in 0..num_of_records {
+        let mut record = Vec::new();
+
+        for (index, variable) in variables.iter() {
+            reader.skip_to(variable.offset.position() + (record_index * record_size))?;
+            let nvals = header.num_of_values(variable);
+            let varslab = if padding {
+                read_values(reader, variable.nc_type, nvals)?
+            } else {
+                read_values_unpadded(reader, variable.nc_type, nvals)?
+            };
+            record.push((*index, NetCDFVarSlab{varslab}));
+        }
+
+        record.sort_by_key(|(index, _)| *index);
+        result.push(NetCDFRecord{record: record.into_iter().map(|(_, var_slab)| var_slab).collect()});
+    }
+
     Ok(result)
 }
@@ -0,0 +1,481 @@
+// Rust modules
+use std::path::Path;
+use std::fs::{File, OpenOptions};
+use std::collections::HashSet;
+use std::{io::Read, io::Seek, io::SeekFrom, io::Write};
+
+// External modules
+use log::{info, debug};
+
+// Internal modules
+use crate::netcdf::*;
+use crate::reader::read_header;
+use crate::writer::{update_layout, write_current_header, encode_values, write_fill_padding,
+    NetCDFWriteOptions, SectionBegin};
+use crate::builder::{NetCDFDimensionLength, check_name, check_attributes, check_fill_value, check_type};
+
+
+/// Size of the buffer used to move data inside the file.
+const MOVE_BUFFER_SIZE: usize = 1 << 20;
+
+/// Opens an existing file to add dimensions, variables and attributes.
+pub fn open_redef<T: AsRef<Path>>(path: T) -> Result<NetCDFRedefiner<File>, NetCDFError> {
+    let file_path = path.as_ref();
+    info!("redef.rs, open_redef, trying to open file: '{}'", file_path.display());
+    let file = OpenOptions::new().read(true).write(true).open(file_path)?;
+    open_redefiner(file)
+}
+
+pub fn open_redefiner<F: Read + Write + Seek>(mut file: F) -> Result<NetCDFRedefiner<F>, NetCDFError> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut header = read_header(&mut file)?;
+    let file_size = file.seek(SeekFrom::End(0))?;
+    header.resolve_streaming(file_size);
+    header.check_num_of_records(file_size)?;
+
+    Ok(NetCDFRedefiner{header, file, file_size, dimensions: Vec::new(), att_list: Vec::new(),
+        variables: Vec::new(), variable_att_list: Vec::new(), fill_mode: NetCDFFillMode::Fill, errors: Vec::new()})
+}
+
+/// Whether variables that are added to an existing file are
+/// filled with their fill value (NC_FILL) or not (NC_NOFILL).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NetCDFFillMode {
+    #[default]
+    Fill,
+    /// Faster, but the values of the new variables are undefined
+    /// until they are written.
+    NoFill,
+}
+
+struct RedefVariable {
+    name: String,
+    nc_type: NetCDFType,
+    dimensions: Vec<String>,
+}
+
+/// Adds definitions to an existing file, like nc_redef / nc_enddef in the C library:
+///
+/// ```no_run
+/// use netcdfrs::prelude::*;
+///
+/// let header = open_redef("data.nc").unwrap()
+///     .add_dimension("lon", NetCDFDimensionLength::Fixed(3))
+///     .add_variable("elevation", NetCDFType::NCShort, &["lon"])
+///     .add_variable_attribute("elevation", "units", NetCDFArray::Char(b"m".to_vec()))
+///     .enddef()
+///     .unwrap();
+/// ```
+///
+/// Nothing is written before enddef(). The existing data is kept, new variables
+/// are filled with their fill value unless set_fill_mode(NetCDFFillMode::NoFill) is used. If the new header does not fit into
+/// the space in front of the data, the data section is moved towards the end of the file.
+pub struct NetCDFRedefiner<F: Read + Write + Seek> {
+    header: NetCDFHeader,
+    file: F,
+    file_size: u64,
+    dimensions: Vec<(String, NetCDFDimensionLength)>,
+    att_list: Vec<NetCDFAttribute>,
+    variables: Vec<RedefVariable>,
+    variable_att_list: Vec<(String, NetCDFAttribute)>,
+    fill_mode: NetCDFFillMode,
+    errors: Vec<String>,
+}
+
+impl<F: Read + Write + Seek> NetCDFRedefiner<F> {
+    /// The header of the file before any of the new definitions.
+    pub fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    pub fn add_dimension(mut self, name: &str, length: NetCDFDimensionLength) -> NetCDFRedefiner<F> {
+        self.dimensions.push((name.to_string(), length));
+        self
+    }
+
+    /// Adds a global attribute, an existing attribute with the same name is replaced.
+    pub fn add_attribute(mut self, name: &str, values: NetCDFArray) -> NetCDFRedefiner<F> {
+        self.att_list.push(NetCDFAttribute{name: name.to_string(), values});
+        self
+    }
+
+    pub fn add_variable(mut self, name: &str, nc_type: NetCDFType, dimensions: &[&str]) -> NetCDFRedefiner<F> {
+        self.variables.push(RedefVariable{
+            name: name.to_string(),
+            nc_type,
+            dimensions: dimensions.iter().map(|dimension| dimension.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Adds an attribute to an existing or a new variable,
+    /// an existing attribute with the same name is replaced.
+    pub fn add_variable_attribute(mut self, variable: &str, name: &str, values: NetCDFArray) -> NetCDFRedefiner<F> {
+        let known = self.header.var_list.iter().any(|other| other.name == variable) ||
+            self.variables.iter().any(|other| other.name == variable);
+
+        if known {
+            self.variable_att_list.push((variable.to_string(), NetCDFAttribute{name: name.to_string(), values}));
+        } else {
+            self.errors.push(format!("attribute '{}' refers to unknown variable '{}'", name, variable));
+        }
+        self
+    }
+
+    /// Like nc_set_fill in the C library, the default is NetCDFFillMode::Fill.
+    pub fn set_fill_mode(mut self, fill_mode: NetCDFFillMode) -> NetCDFRedefiner<F> {
+        self.fill_mode = fill_mode;
+        self
+    }
+
+    /// Writes the new header, moves the existing data to its new position
+    /// and fills the new variables. Returns the new header.
+    pub fn enddef(self) -> Result<NetCDFHeader, NetCDFError> {
+        self.enddef_with_options(&NetCDFWriteOptions::default())
+    }
+
+    /// Like enddef(), with control over the free space and alignment of the
+    /// data sections, see nc__enddef in the C library.
+    /// The streaming option is ignored, numrecs stays as it is in the file.
+    pub fn enddef_with_options(mut self, options: &NetCDFWriteOptions) -> Result<NetCDFHeader, NetCDFError> {
+        if let Some(error) = self.errors.first() {
+            return Err(NetCDFError::InvalidDefinition(error.clone()))
+        }
+
+        match self.header.version {
+            NetCDFVersion::CDF01 | NetCDFVersion::CDF02 | NetCDFVersion::CDF05 => {}
+            _ => return Err(NetCDFError::WriteVersionNotSupported)
+        }
+
+        let mut new_header = self.define_header()?;
+
+        // Keep the space that is already reserved in front of the data sections
+        let section_begin = SectionBegin{
+            fixed: self.header.var_list.iter().map(|variable| variable.offset.position()).min().unwrap_or(0),
+            record: self.header.record_begin().unwrap_or(0),
+        };
+        update_layout(&mut new_header, self.header.num_of_records(), options, &section_begin)?;
+        debug!("NetCDFRedefiner::enddef_with_options, old: {:?}, new data begin: {:?}, new record begin: {:?}", section_begin,
+            new_header.var_list.iter().map(|variable| variable.offset.position()).min(), new_header.record_begin());
+
+        self.move_data(&new_header)?;
+
+        match self.fill_mode {
+            NetCDFFillMode::Fill => self.fill_variables(&new_header)?,
+            NetCDFFillMode::NoFill => self.extend_file(&new_header)?,
+        }
+
+        // Free space between the fixed size and the record section
+        let fixed_end = new_header.var_list.iter()
+            .filter(|variable| !new_header.is_record_variable(variable))
+            .map(|variable| variable.offset.position() + variable.vsize)
+            .max();
+
+        if let (Some(fixed_end), Some(record_begin)) = (fixed_end, new_header.record_begin()) {
+            self.file.seek(SeekFrom::Start(fixed_end))?;
+            self.file.write_all(&vec![0; (record_begin - fixed_end) as usize])?;
+        }
+
+        // The header is written last, the old data in front of the
+        // data section must have been moved away before.
+        let mut buffer = Vec::new();
+        write_current_header(&mut buffer, &new_header)?;
+
+        if let Some(begin) = new_header.var_list.iter().map(|variable| variable.offset.position()).min() {
+            buffer.resize(begin as usize, 0);
+        }
+
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&buffer)?;
+        self.file.flush()?;
+
+        Ok(new_header)
+    }
+
+    /// The old header with all the new definitions, vsize and offsets are not set yet.
+    fn define_header(&self) -> Result<NetCDFHeader, NetCDFError> {
+        let mut header = self.header.clone();
+        let version = header.version;
+
+        for (name, length) in self.dimensions.iter() {
+            check_name(name)?;
+
+            if header.dim_list.iter().any(|dimension| dimension.name == *name) {
+                return Err(NetCDFError::InvalidDefinition(format!("dimension '{}' is defined twice", name)))
+            }
+
+            let length = match length {
+                NetCDFDimensionLength::Fixed(0) => {
+                    return Err(NetCDFError::InvalidDefinition(format!("dimension '{}' has length 0", name)))
+                }
+                NetCDFDimensionLength::Fixed(n) => *n,
+                NetCDFDimensionLength::Unlimited => {
+                    if header.record_dimension_id().is_some() {
+                        return Err(NetCDFError::InvalidDefinition(format!(
+                            "dimension '{}': only one unlimited dimension is allowed", name)))
+                    }
+                    // The record dimension has length 0 in the header
+                    0
+                }
+            };
+
+            header.dim_list.push(NetCDFDimension{name: name.clone(), length});
+        }
+
+        for attribute in self.att_list.iter() {
+            replace_attribute(&mut header.att_list, attribute);
+        }
+
+        let mut names: HashSet<String> = header.var_list.iter().map(|variable| variable.name.clone()).collect();
+
+        for variable in self.variables.iter() {
+            check_name(&variable.name)?;
+            check_type(version, &variable.name, variable.nc_type)?;
+
+            if !names.insert(variable.name.clone()) {
+                return Err(NetCDFError::InvalidDefinition(format!("variable '{}' is defined twice", variable.name)))
+            }
+
+            let mut dimid = Vec::new();
+
+            for (i, dimension) in variable.dimensions.iter().enumerate() {
+                let dim_id = header.dim_list.iter().position(|other| other.name == *dimension)
+                    .ok_or_else(|| NetCDFError::InvalidDefinition(format!("variable '{}' refers to unknown dimension '{}'",
+                        variable.name, dimension)))?;
+
+                if i > 0 && header.dim_list[dim_id].length == 0 {
+                    return Err(NetCDFError::InvalidDefinition(format!(
+                        "variable '{}' can only use the unlimited dimension '{}' as its first dimension",
+                        variable.name, dimension)))
+                }
+
+                dimid.push(dim_id as u32);
+            }
+
+            let offset = match version {
+                NetCDFVersion::CDF01 => NetCDFOffset::Pos32(0),
+                _ => NetCDFOffset::Pos64(0),
+            };
+
+            header.var_list.push(NetCDFVariable{name: variable.name.clone(), dimid, att_list: Vec::new(),
+                nc_type: variable.nc_type, vsize: 0, offset});
+        }
+
+        for (name, attribute) in self.variable_att_list.iter() {
+            if let Some(variable) = header.var_list.iter_mut().find(|other| other.name == *name) {
+                replace_attribute(&mut variable.att_list, attribute);
+            }
+        }
+
+        check_attributes(version, &header.att_list)?;
+
+        for variable in header.var_list.iter() {
+            check_attributes(version, &variable.att_list)?;
+            check_fill_value(&variable.name, variable.nc_type, &variable.att_list)?;
+        }
+
+        Ok(header)
+    }
+
+    /// Moves the data of all existing variables from the old to the new offsets.
+    fn move_data(&mut self, new_header: &NetCDFHeader) -> Result<(), NetCDFError> {
+        let old_header = &self.header;
+        let num_of_records = old_header.num_of_records();
+        let old_record_size = old_header.record_size();
+        let new_record_size = new_header.record_size();
+        let mut chunks = Vec::new();
+
+        // Existing variables keep their index in the var_list
+        for (old, new) in old_header.var_list.iter().zip(new_header.var_list.iter()) {
+            let size = slot_size(old_header, old);
+
+            if old_header.is_record_variable(old) {
+                for record_index in 0..num_of_records {
+                    chunks.push(Chunk{
+                        from: old.offset.position() + (record_index * old_record_size),
+                        to: new.offset.position() + (record_index * new_record_size),
+                        size,
+                    });
+                }
+            } else {
+                chunks.push(Chunk{from: old.offset.position(), to: new.offset.position(), size});
+            }
+        }
+
+        // The padding of the last variable may be missing at the end of the file
+        for chunk in chunks.iter_mut() {
+            chunk.size = chunk.size.min(self.file_size.saturating_sub(chunk.from));
+        }
+
+        chunks.sort_by_key(|chunk| chunk.from);
+        let chunks = merge_chunks(chunks);
+        debug!("NetCDFRedefiner::move_data, number of chunks: {}", chunks.len());
+
+        let same_order = chunks.windows(2).all(|pair| pair[0].to + pair[0].size <= pair[1].to);
+
+        if same_order {
+            // Chunks moving towards the start of the file are moved first, from the front,
+            // then the ones moving towards the end, from the back. This way no chunk
+            // overwrites data that has not been moved yet.
+            for chunk in chunks.iter().filter(|chunk| chunk.to < chunk.from) {
+                move_bytes(&mut self.file, chunk)?;
+            }
+
+            for chunk in chunks.iter().rev().filter(|chunk| chunk.to > chunk.from) {
+                move_bytes(&mut self.file, chunk)?;
+            }
+        } else {
+            // The variables are not stored in the order of the var_list
+            info!("redef.rs, move_data, data is reordered in memory");
+            let mut buffers = Vec::new();
+
+            for chunk in chunks.iter() {
+                let mut buffer = vec![0; chunk.size as usize];
+                self.file.seek(SeekFrom::Start(chunk.from))?;
+                self.file.read_exact(&mut buffer)?;
+                buffers.push(buffer);
+            }
+
+            for (chunk, buffer) in chunks.iter().zip(buffers.iter()) {
+                self.file.seek(SeekFrom::Start(chunk.to))?;
+                self.file.write_all(buffer)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes fill values for the new variables and the padding of record
+    /// variables that was not needed in the old file.
+    fn fill_variables(&mut self, new_header: &NetCDFHeader) -> Result<(), NetCDFError> {
+        let num_of_records = self.header.num_of_records();
+        let record_size = new_header.record_size();
+        let old_padding = self.header.pads_records();
+        let padding = new_header.pads_records();
+
+        for (index, variable) in new_header.var_list.iter().enumerate() {
+            let is_new = index >= self.header.var_list.len();
+            let nvals = new_header.num_of_values(variable);
+            let size_in_bytes = nvals * variable.nc_type.size_in_bytes();
+
+            if new_header.is_record_variable(variable) {
+                if is_new {
+                    let buffer = encode_values(&NetCDFArray::repeat(&variable.fill_value(), nvals));
+
+                    for record_index in 0..num_of_records {
+                        self.file.seek(SeekFrom::Start(variable.offset.position() + (record_index * record_size)))?;
+                        self.file.write_all(&buffer)?;
+
+                        if padding {
+                            write_fill_padding(&mut self.file, variable, size_in_bytes)?;
+                        }
+                    }
+                } else if padding && !old_padding {
+                    for record_index in 0..num_of_records {
+                        let position = variable.offset.position() + (record_index * record_size) + size_in_bytes as u64;
+                        self.file.seek(SeekFrom::Start(position))?;
+                        write_fill_padding(&mut self.file, variable, size_in_bytes)?;
+                    }
+                }
+            } else if is_new {
+                self.file.seek(SeekFrom::Start(variable.offset.position()))?;
+                let buffer = encode_values(&NetCDFArray::repeat(&variable.fill_value(), nvals));
+                self.file.write_all(&buffer)?;
+                write_fill_padding(&mut self.file, variable, size_in_bytes)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Without filling, the file still needs to be large enough
+    /// for all the variables.
+    fn extend_file(&mut self, new_header: &NetCDFHeader) -> Result<(), NetCDFError> {
+        let num_of_records = self.header.num_of_records();
+        let fixed_end = new_header.var_list.iter()
+            .filter(|variable| !new_header.is_record_variable(variable))
+            .map(|variable| variable.offset.position() + variable.vsize)
+            .max()
+            .unwrap_or(0);
+        let record_end = match new_header.record_begin() {
+            Some(record_begin) if num_of_records > 0 => record_begin + (num_of_records * new_header.record_size()),
+            _ => 0,
+        };
+        let data_end = fixed_end.max(record_end);
+        let file_size = self.file.seek(SeekFrom::End(0))?;
+        debug!("NetCDFRedefiner::extend_file, file size: {}, end of data: {}", file_size, data_end);
+
+        if data_end > file_size {
+            self.file.seek(SeekFrom::Start(data_end - 1))?;
+            self.file.write_all(&[0])?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A block of data that is moved from one position in the file to another.
+struct Chunk {
+    from: u64,
+    to: u64,
+    size: u64,
+}
+
+/// Joins chunks that follow each other and are moved by the same distance.
+fn merge_chunks(chunks: Vec<Chunk>) -> Vec<Chunk> {
+    let mut result: Vec<Chunk> = Vec::new();
+
+    for chunk in chunks {
+        match result.last_mut() {
+            Some(last) if last.from + last.size == chunk.from && last.to + last.size == chunk.to => {
+                last.size += chunk.size;
+            }
+            _ => result.push(chunk),
+        }
+    }
+
+    result
+}
+
+/// Moves a chunk with a fixed size buffer. The source and the destination may overlap.
+fn move_bytes<F: Read + Write + Seek>(file: &mut F, chunk: &Chunk) -> Result<(), NetCDFError> {
+    let mut buffer = vec![0; MOVE_BUFFER_SIZE.min(chunk.size as usize)];
+    let mut done = 0;
+
+    while done < chunk.size {
+        let n = (buffer.len() as u64).min(chunk.size - done);
+
+        // Copy from the back when moving towards the end of the file
+        let position = if chunk.to > chunk.from {
+            chunk.size - done - n
+        } else {
+            done
+        };
+
+        file.seek(SeekFrom::Start(chunk.from + position))?;
+        file.read_exact(&mut buffer[..n as usize])?;
+        file.seek(SeekFrom::Start(chunk.to + position))?;
+        file.write_all(&buffer[..n as usize])?;
+        done += n;
+    }
+
+    Ok(())
+}
+
+/// Size of the data of a variable in the data section, or in one record.
+fn slot_size(header: &NetCDFHeader, variable: &NetCDFVariable) -> u64 {
+    let size_in_bytes = header.data_size(variable);
+
+    if header.is_record_variable(variable) && !header.pads_records() {
+        size_in_bytes
+    } else {
+        padded_size(size_in_bytes)
+    }
+}
+
+fn replace_attribute(att_list: &mut Vec<NetCDFAttribute>, attribute: &NetCDFAttribute) {
+    match att_list.iter_mut().find(|other| other.name == attribute.name) {
+        Some(other) => other.values = attribute.values.clone(),
+        None => att_list.push(attribute.clone()),
+    }
+}
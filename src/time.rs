@@ -0,0 +1,463 @@
+// Rust modules
+use std::{fmt, fmt::Display, fmt::Formatter};
+
+// External modules
+use log::debug;
+
+// Internal modules
+use crate::netcdf::*;
+
+// Time coordinates are described in the CF conventions:
+// https://cfconventions.org/Data/cf-conventions/cf-conventions-1.10/cf-conventions.html#time-coordinate
+
+const MICROSECONDS_PER_DAY: i64 = 86_400_000_000;
+
+/// Julian day number of 1582-10-15, the first day of the Gregorian calendar.
+const GREGORIAN_START: i64 = 2_299_161;
+
+/// Keeps the day numbers of the calendars inside of an i64. Microseconds
+/// only reach about 290000 years, the rest is checked when converting.
+const MAX_YEAR: i64 = 1_000_000_000;
+
+const DAYS_IN_MONTH: [u32; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+
+/// The calendars defined by the CF conventions. Years are counted
+/// astronomically in all calendars, the year before 1 is 0.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetCDFCalendar {
+    /// Julian calendar before 1582-10-15, Gregorian calendar after it (also "gregorian").
+    /// This is the default if a variable has no calendar attribute.
+    Standard,
+    ProlepticGregorian,
+    /// Every year has 365 days (also "365_day").
+    NoLeap,
+    /// Every year has 366 days (also "366_day").
+    AllLeap,
+    /// Every month has 30 days.
+    Day360,
+    Julian,
+}
+
+impl NetCDFCalendar {
+    /// Parses the value of a calendar attribute, the case is ignored.
+    pub fn from_name(name: &str) -> Result<NetCDFCalendar, NetCDFError> {
+        match name.trim().to_lowercase().as_str() {
+            "standard" | "gregorian" => Ok(NetCDFCalendar::Standard),
+            "proleptic_gregorian" => Ok(NetCDFCalendar::ProlepticGregorian),
+            "noleap" | "365_day" => Ok(NetCDFCalendar::NoLeap),
+            "all_leap" | "366_day" => Ok(NetCDFCalendar::AllLeap),
+            "360_day" => Ok(NetCDFCalendar::Day360),
+            "julian" => Ok(NetCDFCalendar::Julian),
+            _ => Err(NetCDFError::InvalidTime(format!("unknown calendar: '{}'", name))),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NetCDFCalendar::Standard => "standard",
+            NetCDFCalendar::ProlepticGregorian => "proleptic_gregorian",
+            NetCDFCalendar::NoLeap => "noleap",
+            NetCDFCalendar::AllLeap => "all_leap",
+            NetCDFCalendar::Day360 => "360_day",
+            NetCDFCalendar::Julian => "julian",
+        }
+    }
+
+    pub fn is_leap_year(&self, year: i64) -> bool {
+        match self {
+            NetCDFCalendar::Standard => {
+                if year > 1582 {
+                    is_gregorian_leap_year(year)
+                } else {
+                    year.rem_euclid(4) == 0
+                }
+            }
+            NetCDFCalendar::ProlepticGregorian => is_gregorian_leap_year(year),
+            NetCDFCalendar::NoLeap => false,
+            NetCDFCalendar::AllLeap => true,
+            NetCDFCalendar::Day360 => false,
+            NetCDFCalendar::Julian => year.rem_euclid(4) == 0,
+        }
+    }
+
+    /// The month must be in 1..=12, callers check it before.
+    fn days_in_month(&self, year: i64, month: u32) -> u32 {
+        match (self, month) {
+            (NetCDFCalendar::Day360, _) => 30,
+            (_, 2) if self.is_leap_year(year) => 29,
+            _ => DAYS_IN_MONTH[(month - 1) as usize],
+        }
+    }
+
+    /// Continuous count of days, only differences between two
+    /// day numbers of the same calendar are meaningful.
+    fn day_number(&self, year: i64, month: u32, day: u32) -> Result<i64, NetCDFError> {
+        let (month, day) = (month as i64, day as i64);
+
+        match self {
+            NetCDFCalendar::Standard => {
+                if (year, month, day) >= (1582, 10, 15) {
+                    Ok(gregorian_day_number(year, month, day))
+                } else if (year, month, day) <= (1582, 10, 4) {
+                    Ok(julian_day_number(year, month, day))
+                } else {
+                    Err(NetCDFError::InvalidTime(format!(
+                        "{}-10-{} does not exist in the standard calendar", year, day)))
+                }
+            }
+            NetCDFCalendar::ProlepticGregorian => Ok(gregorian_day_number(year, month, day)),
+            NetCDFCalendar::Julian => Ok(julian_day_number(year, month, day)),
+            NetCDFCalendar::NoLeap | NetCDFCalendar::AllLeap => {
+                let days_before_month: i64 = (1..month).map(|m| self.days_in_month(year, m as u32) as i64).sum();
+                let days_per_year = if *self == NetCDFCalendar::NoLeap { 365 } else { 366 };
+                Ok((year * days_per_year) + days_before_month + day - 1)
+            }
+            NetCDFCalendar::Day360 => Ok((year * 360) + ((month - 1) * 30) + day - 1),
+        }
+    }
+
+    /// The reverse of day_number(), returns year, month and day.
+    fn date(&self, day_number: i64) -> (i64, u32, u32) {
+        match self {
+            NetCDFCalendar::Standard => {
+                if day_number >= GREGORIAN_START {
+                    gregorian_date(day_number)
+                } else {
+                    julian_date(day_number)
+                }
+            }
+            NetCDFCalendar::ProlepticGregorian => gregorian_date(day_number),
+            NetCDFCalendar::Julian => julian_date(day_number),
+            NetCDFCalendar::NoLeap | NetCDFCalendar::AllLeap | NetCDFCalendar::Day360 => {
+                let days_per_year = match self {
+                    NetCDFCalendar::NoLeap => 365,
+                    NetCDFCalendar::AllLeap => 366,
+                    _ => 360,
+                };
+                let year = day_number.div_euclid(days_per_year);
+                let mut day_of_year = day_number.rem_euclid(days_per_year) as u32;
+                let mut month = 1;
+
+                while day_of_year >= self.days_in_month(year, month) {
+                    day_of_year -= self.days_in_month(year, month);
+                    month += 1;
+                }
+
+                (year, month, day_of_year + 1)
+            }
+        }
+    }
+}
+
+fn is_gregorian_leap_year(year: i64) -> bool {
+    (year.rem_euclid(4) == 0 && year.rem_euclid(100) != 0) || year.rem_euclid(400) == 0
+}
+
+// Julian day numbers, see E. G. Richards, "Calendars" in the
+// Explanatory Supplement to the Astronomical Almanac.
+
+fn gregorian_day_number(year: i64, month: i64, day: i64) -> i64 {
+    let a = (14 - month) / 12;
+    let y = year + 4800 - a;
+    let m = month + (12 * a) - 3;
+    day + (((153 * m) + 2) / 5) + (365 * y) + y.div_euclid(4) - y.div_euclid(100) + y.div_euclid(400) - 32045
+}
+
+fn julian_day_number(year: i64, month: i64, day: i64) -> i64 {
+    let a = (14 - month) / 12;
+    let y = year + 4800 - a;
+    let m = month + (12 * a) - 3;
+    day + (((153 * m) + 2) / 5) + (365 * y) + y.div_euclid(4) - 32083
+}
+
+fn gregorian_date(day_number: i64) -> (i64, u32, u32) {
+    let a = day_number + 32044;
+    let b = ((4 * a) + 3).div_euclid(146_097);
+    let c = a - (146_097 * b).div_euclid(4);
+    date_from_parts(b, c)
+}
+
+fn julian_date(day_number: i64) -> (i64, u32, u32) {
+    date_from_parts(0, day_number + 32082)
+}
+
+fn date_from_parts(b: i64, c: i64) -> (i64, u32, u32) {
+    let d = ((4 * c) + 3).div_euclid(1461);
+    let e = c - (1461 * d).div_euclid(4);
+    let m = ((5 * e) + 2) / 153;
+    let day = e - (((153 * m) + 2) / 5) + 1;
+    let month = m + 3 - (12 * (m / 10));
+    let year = (100 * b) + d - 4800 + (m / 10);
+    (year, month as u32, day as u32)
+}
+
+/// A date and time in one of the CF calendars, always in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NetCDFDateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub microsecond: u32,
+}
+
+impl NetCDFDateTime {
+    pub fn new(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> NetCDFDateTime {
+        NetCDFDateTime{year, month, day, hour, minute, second, microsecond: 0}
+    }
+
+    fn check(&self, calendar: NetCDFCalendar) -> Result<(), NetCDFError> {
+        let valid = self.year.abs() <= MAX_YEAR && self.month >= 1 && self.month <= 12 &&
+            self.day >= 1 && self.day <= calendar.days_in_month(self.year, self.month) &&
+            self.hour < 24 && self.minute < 60 && self.second < 60 && self.microsecond < 1_000_000;
+
+        if valid {
+            Ok(())
+        } else {
+            Err(NetCDFError::InvalidTime(format!("{} is not a valid date in the {} calendar", self, calendar.name())))
+        }
+    }
+
+    /// Microseconds since day 0 of the calendar.
+    fn to_microseconds(self, calendar: NetCDFCalendar) -> Result<i64, NetCDFError> {
+        self.check(calendar)?;
+        let day_number = calendar.day_number(self.year, self.month, self.day)?;
+        let seconds = ((self.hour as i64 * 60) + self.minute as i64) * 60 + self.second as i64;
+
+        day_number.checked_mul(MICROSECONDS_PER_DAY)
+            .and_then(|microseconds| microseconds.checked_add((seconds * 1_000_000) + self.microsecond as i64))
+            .ok_or_else(|| NetCDFError::InvalidTime(format!("{} is out of range", self)))
+    }
+
+    fn from_microseconds(microseconds: i64, calendar: NetCDFCalendar) -> NetCDFDateTime {
+        let (year, month, day) = calendar.date(microseconds.div_euclid(MICROSECONDS_PER_DAY));
+        let time = microseconds.rem_euclid(MICROSECONDS_PER_DAY);
+        let seconds = time / 1_000_000;
+
+        NetCDFDateTime{year, month, day,
+            hour: (seconds / 3600) as u32,
+            minute: ((seconds / 60) % 60) as u32,
+            second: (seconds % 60) as u32,
+            microsecond: (time % 1_000_000) as u32,
+        }
+    }
+}
+
+impl Display for NetCDFDateTime {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(formatter, "{:04}-{:02}-{:02} {:02}:{:02}:{:02}", self.year, self.month, self.day,
+            self.hour, self.minute, self.second)?;
+
+        if self.microsecond != 0 {
+            write!(formatter, ".{:06}", self.microsecond)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetCDFTimeUnit {
+    Microseconds,
+    Milliseconds,
+    Seconds,
+    Minutes,
+    Hours,
+    Days,
+}
+
+impl NetCDFTimeUnit {
+    /// Accepts the UDUNITS names and abbreviations.
+    pub fn from_name(name: &str) -> Result<NetCDFTimeUnit, NetCDFError> {
+        match name.to_lowercase().as_str() {
+            "microseconds" | "microsecond" | "usec" | "usecs" | "us" => Ok(NetCDFTimeUnit::Microseconds),
+            "milliseconds" | "millisecond" | "msec" | "msecs" | "ms" => Ok(NetCDFTimeUnit::Milliseconds),
+            "seconds" | "second" | "sec" | "secs" | "s" => Ok(NetCDFTimeUnit::Seconds),
+            "minutes" | "minute" | "min" | "mins" => Ok(NetCDFTimeUnit::Minutes),
+            "hours" | "hour" | "hr" | "hrs" | "h" => Ok(NetCDFTimeUnit::Hours),
+            "days" | "day" | "d" => Ok(NetCDFTimeUnit::Days),
+            _ => Err(NetCDFError::InvalidTime(format!("unknown time unit: '{}'", name))),
+        }
+    }
+
+    pub fn microseconds(&self) -> i64 {
+        match self {
+            NetCDFTimeUnit::Microseconds => 1,
+            NetCDFTimeUnit::Milliseconds => 1_000,
+            NetCDFTimeUnit::Seconds => 1_000_000,
+            NetCDFTimeUnit::Minutes => 60_000_000,
+            NetCDFTimeUnit::Hours => 3_600_000_000,
+            NetCDFTimeUnit::Days => MICROSECONDS_PER_DAY,
+        }
+    }
+}
+
+/// The units of a time coordinate, for example "days since 1850-01-01 00:00:00",
+/// together with its calendar. Converts between numeric values and dates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetCDFTimeUnits {
+    pub unit: NetCDFTimeUnit,
+    /// The reference time converted to UTC
+    pub reference: NetCDFDateTime,
+    pub calendar: NetCDFCalendar,
+}
+
+impl NetCDFTimeUnits {
+    /// Parses "<unit> since <date>[ <time>][ <time zone>]", the date and time
+    /// may also be separated by 'T' as in ISO 8601.
+    pub fn parse(units: &str, calendar: NetCDFCalendar) -> Result<NetCDFTimeUnits, NetCDFError> {
+        let invalid = || NetCDFError::InvalidTime(format!("invalid time units: '{}'", units));
+        let (unit, rest) = units.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let unit = NetCDFTimeUnit::from_name(unit)?;
+        let (since, reference) = rest.trim_start().split_once(char::is_whitespace).ok_or_else(invalid)?;
+
+        if !since.eq_ignore_ascii_case("since") {
+            return Err(invalid())
+        }
+
+        let reference = parse_reference(reference, calendar).ok_or_else(invalid)?;
+        debug!("NetCDFTimeUnits::parse, units: '{}', reference: {}", units, reference);
+
+        Ok(NetCDFTimeUnits{unit, reference, calendar})
+    }
+
+    /// Uses the units and calendar attributes of a variable.
+    /// Without a calendar attribute the standard calendar is used.
+    pub fn from_variable(variable: &NetCDFVariable) -> Result<NetCDFTimeUnits, NetCDFError> {
+        let attribute_string = |name: &str| variable.attribute(name)
+            .and_then(|attribute| attribute.values.as_string());
+
+        let units = attribute_string("units")
+            .ok_or_else(|| NetCDFError::InvalidTime(format!("variable '{}' has no units attribute", variable.name)))?;
+        let calendar = match attribute_string("calendar") {
+            Some(calendar) => NetCDFCalendar::from_name(&calendar)?,
+            None => NetCDFCalendar::Standard,
+        };
+
+        NetCDFTimeUnits::parse(&units, calendar)
+    }
+
+    /// Converts a numeric time value to a date, rounded to microseconds.
+    pub fn decode(&self, value: f64) -> Result<NetCDFDateTime, NetCDFError> {
+        let offset = (value * self.unit.microseconds() as f64).round();
+
+        if !offset.is_finite() || offset.abs() > i64::MAX as f64 / 2.0 {
+            return Err(NetCDFError::InvalidTime(format!("time value {} is out of range", value)))
+        }
+
+        let microseconds = self.reference.to_microseconds(self.calendar)?.checked_add(offset as i64)
+            .ok_or_else(|| NetCDFError::InvalidTime(format!("time value {} is out of range", value)))?;
+        Ok(NetCDFDateTime::from_microseconds(microseconds, self.calendar))
+    }
+
+    /// Converts a date to a numeric time value.
+    pub fn encode(&self, datetime: &NetCDFDateTime) -> Result<f64, NetCDFError> {
+        let microseconds = datetime.to_microseconds(self.calendar)?.checked_sub(self.reference.to_microseconds(self.calendar)?)
+            .ok_or_else(|| NetCDFError::InvalidTime(format!("{} is out of range", datetime)))?;
+        let unit = self.unit.microseconds();
+
+        // Exact whole values do not lose precision in the division
+        if microseconds % unit == 0 {
+            Ok((microseconds / unit) as f64)
+        } else {
+            Ok(microseconds as f64 / unit as f64)
+        }
+    }
+
+    /// Decodes masked or unpacked data, masked values are None.
+    pub fn decode_data(&self, data: &NetCDFMaskedData) -> Result<Vec<Option<NetCDFDateTime>>, NetCDFError> {
+        data.to_f64_options().into_iter()
+            .map(|value| value.map(|value| self.decode(value)).transpose())
+            .collect()
+    }
+}
+
+impl Display for NetCDFTimeUnits {
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), fmt::Error> {
+        let unit = match self.unit {
+            NetCDFTimeUnit::Microseconds => "microseconds",
+            NetCDFTimeUnit::Milliseconds => "milliseconds",
+            NetCDFTimeUnit::Seconds => "seconds",
+            NetCDFTimeUnit::Minutes => "minutes",
+            NetCDFTimeUnit::Hours => "hours",
+            NetCDFTimeUnit::Days => "days",
+        };
+        write!(formatter, "{} since {}", unit, self.reference)
+    }
+}
+
+/// Parses the reference time of the units and converts it to UTC.
+fn parse_reference(text: &str, calendar: NetCDFCalendar) -> Option<NetCDFDateTime> {
+    let text = text.trim();
+    let (date, rest) = match text.find(|c: char| c == 'T' || c.is_whitespace()) {
+        Some(index) => (&text[..index], text[index + 1..].trim()),
+        None => (text, ""),
+    };
+
+    // The year may be negative
+    let (sign, date) = match date.strip_prefix('-') {
+        Some(date) => (-1, date),
+        None => (1, date),
+    };
+    let mut date_parts = date.split('-');
+    let year: i64 = sign * date_parts.next()?.parse::<i64>().ok()?;
+    let month: u32 = date_parts.next().map_or(Some(1), |month| month.parse().ok())?;
+    let day: u32 = date_parts.next().map_or(Some(1), |day| day.parse().ok())?;
+
+    if date_parts.next().is_some() {
+        return None
+    }
+
+    // Time and time zone, "12:30:00.5 +01:00", "12:30Z", "12 UTC"
+    let (time, zone) = split_time_zone(rest)?;
+    let mut time_parts = time.split(':');
+    let hour: u32 = time_parts.next().filter(|hour| !hour.is_empty()).map_or(Some(0), |hour| hour.parse().ok())?;
+    let minute: u32 = time_parts.next().map_or(Some(0), |minute| minute.parse().ok())?;
+    let seconds: f64 = time_parts.next().map_or(Some(0.0), |second| second.parse().ok())?;
+
+    if time_parts.next().is_some() || !(0.0..60.0).contains(&seconds) {
+        return None
+    }
+
+    let microseconds = (seconds * 1_000_000.0).round() as u32;
+    let datetime = NetCDFDateTime{year, month, day, hour, minute,
+        second: microseconds / 1_000_000, microsecond: microseconds % 1_000_000};
+    let local = datetime.to_microseconds(calendar).ok()?;
+
+    let utc = zone.checked_mul(60_000_000).and_then(|zone| local.checked_sub(zone))?;
+
+    Some(NetCDFDateTime::from_microseconds(utc, calendar))
+}
+
+/// Splits the time from the time zone, returns the offset of the time zone in minutes.
+fn split_time_zone(text: &str) -> Option<(&str, i64)> {
+    let text = text.trim();
+
+    let (time, zone) = if let Some(index) = text.find(['+', '-']) {
+        (text[..index].trim(), &text[index..])
+    } else if let Some(index) = text.find(|c: char| c.is_alphabetic()) {
+        (text[..index].trim(), &text[index..])
+    } else {
+        (text, "")
+    };
+
+    let zone = zone.trim();
+
+    let offset = match zone {
+        "" | "Z" | "UTC" | "GMT" => 0,
+        _ => {
+            let sign = if zone.starts_with('-') { -1 } else { 1 };
+            let zone = zone.trim_start_matches(['+', '-']);
+            let (hours, minutes) = match zone.split_once(':') {
+                Some((hours, minutes)) => (hours.parse::<i64>().ok()?, minutes.parse::<i64>().ok()?),
+                None if zone.len() == 4 => (zone[..2].parse::<i64>().ok()?, zone[2..].parse::<i64>().ok()?),
+                None => (zone.parse::<i64>().ok()?, 0),
+            };
+            sign * hours.checked_mul(60)?.checked_add(minutes)?
+        }
+    };
+
+    Some((time, offset))
+}
@@ -0,0 +1,308 @@
+// Rust modules
+use std::f64::consts::PI;
+
+// External modules
+use log::debug;
+
+// Internal modules
+use crate::netcdf::*;
+
+// Unit strings follow the UDUNITS-2 syntax, which is used by the CF conventions:
+// https://docs.unidata.ucar.edu/udunits/current/
+
+/// Exponents of the SI base units: m, kg, s, A, K, mol, cd
+type Dimensions = [i8; 7];
+
+const DIMENSIONLESS: Dimensions = [0, 0, 0, 0, 0, 0, 0];
+const LENGTH: Dimensions = [1, 0, 0, 0, 0, 0, 0];
+const MASS: Dimensions = [0, 1, 0, 0, 0, 0, 0];
+const TIME: Dimensions = [0, 0, 1, 0, 0, 0, 0];
+const CURRENT: Dimensions = [0, 0, 0, 1, 0, 0, 0];
+const TEMPERATURE: Dimensions = [0, 0, 0, 0, 1, 0, 0];
+const AMOUNT: Dimensions = [0, 0, 0, 0, 0, 1, 0];
+const LUMINOUS_INTENSITY: Dimensions = [0, 0, 0, 0, 0, 0, 1];
+const FREQUENCY: Dimensions = [0, 0, -1, 0, 0, 0, 0];
+const VELOCITY: Dimensions = [1, 0, -1, 0, 0, 0, 0];
+const VOLUME: Dimensions = [3, 0, 0, 0, 0, 0, 0];
+const FORCE: Dimensions = [1, 1, -2, 0, 0, 0, 0];
+const PRESSURE: Dimensions = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: Dimensions = [2, 1, -2, 0, 0, 0, 0];
+const POWER: Dimensions = [2, 1, -3, 0, 0, 0, 0];
+const CHARGE: Dimensions = [0, 0, 1, 1, 0, 0, 0];
+const VOLTAGE: Dimensions = [2, 1, -3, -1, 0, 0, 0];
+const RESISTANCE: Dimensions = [2, 1, -3, -2, 0, 0, 0];
+
+/// Name, factor, offset and dimensions of the known units.
+const UNITS: [(&str, f64, f64, Dimensions); 61] = [
+    // SI base units
+    ("m", 1.0, 0.0, LENGTH),
+    ("meter", 1.0, 0.0, LENGTH),
+    ("metre", 1.0, 0.0, LENGTH),
+    ("g", 1e-3, 0.0, MASS),
+    ("gram", 1e-3, 0.0, MASS),
+    ("s", 1.0, 0.0, TIME),
+    ("sec", 1.0, 0.0, TIME),
+    ("second", 1.0, 0.0, TIME),
+    ("A", 1.0, 0.0, CURRENT),
+    ("ampere", 1.0, 0.0, CURRENT),
+    ("K", 1.0, 0.0, TEMPERATURE),
+    ("kelvin", 1.0, 0.0, TEMPERATURE),
+    ("degK", 1.0, 0.0, TEMPERATURE),
+    ("deg_K", 1.0, 0.0, TEMPERATURE),
+    ("mol", 1.0, 0.0, AMOUNT),
+    ("mole", 1.0, 0.0, AMOUNT),
+    ("cd", 1.0, 0.0, LUMINOUS_INTENSITY),
+    ("candela", 1.0, 0.0, LUMINOUS_INTENSITY),
+    // Derived units
+    ("Hz", 1.0, 0.0, FREQUENCY),
+    ("hertz", 1.0, 0.0, FREQUENCY),
+    ("N", 1.0, 0.0, FORCE),
+    ("newton", 1.0, 0.0, FORCE),
+    ("Pa", 1.0, 0.0, PRESSURE),
+    ("pascal", 1.0, 0.0, PRESSURE),
+    ("J", 1.0, 0.0, ENERGY),
+    ("joule", 1.0, 0.0, ENERGY),
+    ("W", 1.0, 0.0, POWER),
+    ("watt", 1.0, 0.0, POWER),
+    ("C", 1.0, 0.0, CHARGE),
+    ("coulomb", 1.0, 0.0, CHARGE),
+    ("V", 1.0, 0.0, VOLTAGE),
+    ("volt", 1.0, 0.0, VOLTAGE),
+    ("ohm", 1.0, 0.0, RESISTANCE),
+    // Temperatures with an offset
+    ("degC", 1.0, 273.15, TEMPERATURE),
+    ("deg_C", 1.0, 273.15, TEMPERATURE),
+    ("celsius", 1.0, 273.15, TEMPERATURE),
+    ("degree_Celsius", 1.0, 273.15, TEMPERATURE),
+    ("degF", 5.0 / 9.0, 459.67 * 5.0 / 9.0, TEMPERATURE),
+    ("deg_F", 5.0 / 9.0, 459.67 * 5.0 / 9.0, TEMPERATURE),
+    ("fahrenheit", 5.0 / 9.0, 459.67 * 5.0 / 9.0, TEMPERATURE),
+    ("degree_Fahrenheit", 5.0 / 9.0, 459.67 * 5.0 / 9.0, TEMPERATURE),
+    // Other units
+    ("min", 60.0, 0.0, TIME),
+    ("minute", 60.0, 0.0, TIME),
+    ("h", 3600.0, 0.0, TIME),
+    ("hr", 3600.0, 0.0, TIME),
+    ("hour", 3600.0, 0.0, TIME),
+    ("d", 86400.0, 0.0, TIME),
+    ("day", 86400.0, 0.0, TIME),
+    ("year", 31_556_925.974_678_4, 0.0, TIME),
+    ("bar", 1e5, 0.0, PRESSURE),
+    ("atm", 101_325.0, 0.0, PRESSURE),
+    ("L", 1e-3, 0.0, VOLUME),
+    ("l", 1e-3, 0.0, VOLUME),
+    ("liter", 1e-3, 0.0, VOLUME),
+    ("litre", 1e-3, 0.0, VOLUME),
+    ("knot", 1852.0 / 3600.0, 0.0, VELOCITY),
+    ("rad", 1.0, 0.0, DIMENSIONLESS),
+    ("radian", 1.0, 0.0, DIMENSIONLESS),
+    ("percent", 0.01, 0.0, DIMENSIONLESS),
+    ("%", 0.01, 0.0, DIMENSIONLESS),
+    ("ppm", 1e-6, 0.0, DIMENSIONLESS),
+];
+
+/// Angles in degrees, including the CF names for latitude and longitude.
+const DEGREES: [&str; 16] = ["degree", "deg", "arc_degree", "angular_degree",
+    "degree_north", "degree_N", "degreeN", "degrees_north", "degrees_N", "degreesN",
+    "degree_east", "degree_E", "degreeE", "degrees_east", "degrees_E", "degreesE"];
+
+const PREFIXES: [(&str, f64); 40] = [
+    ("yotta", 1e24), ("zetta", 1e21), ("exa", 1e18), ("peta", 1e15), ("tera", 1e12), ("giga", 1e9),
+    ("mega", 1e6), ("kilo", 1e3), ("hecto", 1e2), ("deka", 1e1), ("deca", 1e1), ("deci", 1e-1),
+    ("centi", 1e-2), ("milli", 1e-3), ("micro", 1e-6), ("nano", 1e-9), ("pico", 1e-12),
+    ("femto", 1e-15), ("atto", 1e-18), ("zepto", 1e-21), ("yocto", 1e-24),
+    ("Y", 1e24), ("Z", 1e21), ("E", 1e18), ("P", 1e15), ("T", 1e12), ("G", 1e9), ("M", 1e6),
+    ("k", 1e3), ("h", 1e2), ("da", 1e1), ("d", 1e-1), ("c", 1e-2), ("m", 1e-3), ("u", 1e-6),
+    ("µ", 1e-6), ("n", 1e-9), ("p", 1e-12), ("f", 1e-15), ("a", 1e-18),
+];
+
+/// A physical unit as a multiple of SI base units: si_value = value * factor + offset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetCDFUnit {
+    factor: f64,
+    offset: f64,
+    dimensions: Dimensions,
+}
+
+impl NetCDFUnit {
+    /// Parses a unit string like "K", "degC", "m s-1", "kg m-2 s-1", "m/s", "m^2" or "hPa".
+    /// Factors are separated by white space, '.' or '*', exponents are written
+    /// as integers directly after the unit, optionally with '^' or '**'.
+    pub fn parse(text: &str) -> Result<NetCDFUnit, NetCDFError> {
+        let invalid = |reason: &str| NetCDFError::InvalidUnit(format!("'{}': {}", text, reason));
+        let terms = split_terms(text);
+        let mut result = NetCDFUnit{factor: 1.0, offset: 0.0, dimensions: DIMENSIONLESS};
+
+        if terms.is_empty() {
+            return Err(invalid("empty unit"))
+        }
+
+        for (term, divide) in terms.iter() {
+            let (unit, exponent) = parse_term(term).ok_or_else(|| invalid(&format!("unknown unit '{}'", term)))?;
+            let exponent = if *divide { exponent.checked_neg() } else { Some(exponent) }
+                .ok_or_else(|| invalid(&format!("exponent of '{}' out of range", term)))?;
+
+            // As in UDUNITS an offset is only used for a single unit like "degC",
+            // in a product like "degC s-1" it is a temperature difference.
+            if terms.len() == 1 && exponent == 1 {
+                result.offset = unit.offset;
+            }
+
+            result.factor *= unit.factor.powi(exponent as i32);
+            result.dimensions = add_dimensions(&result.dimensions, &unit.dimensions, exponent)
+                .ok_or_else(|| invalid(&format!("exponent of '{}' out of range", term)))?;
+        }
+
+        debug!("NetCDFUnit::parse, '{}': {:?}", text, result);
+        Ok(result)
+    }
+
+    /// Parses the units attribute of a variable.
+    pub fn from_variable(variable: &NetCDFVariable) -> Result<NetCDFUnit, NetCDFError> {
+        let units = variable.attribute("units")
+            .and_then(|attribute| attribute.values.as_string())
+            .ok_or_else(|| NetCDFError::InvalidUnit(format!("variable '{}' has no units attribute", variable.name)))?;
+
+        NetCDFUnit::parse(&units)
+    }
+
+    /// True if both units have the same dimensions, i.e. values can be converted.
+    pub fn is_compatible(&self, other: &NetCDFUnit) -> bool {
+        self.dimensions == other.dimensions
+    }
+
+    pub fn is_dimensionless(&self) -> bool {
+        self.dimensions == DIMENSIONLESS
+    }
+
+    /// Returns scale and offset to convert a value from this unit to the other:
+    /// converted = value * scale + offset.
+    pub fn conversion(&self, to: &NetCDFUnit) -> Result<(f64, f64), NetCDFError> {
+        if !self.is_compatible(to) {
+            return Err(NetCDFError::InvalidUnit(format!("can not convert between dimensions {:?} and {:?}",
+                self.dimensions, to.dimensions)))
+        }
+
+        Ok((self.factor / to.factor, (self.offset - to.offset) / to.factor))
+    }
+
+    pub fn convert(&self, value: f64, to: &NetCDFUnit) -> Result<f64, NetCDFError> {
+        let (scale, offset) = self.conversion(to)?;
+        Ok((value * scale) + offset)
+    }
+
+    /// Converts all values, float values stay float, all other types are converted to double.
+    pub fn convert_values(&self, values: &NetCDFArray, to: &NetCDFUnit) -> Result<NetCDFArray, NetCDFError> {
+        let (scale, offset) = self.conversion(to)?;
+
+        match values {
+            NetCDFArray::Float(v) => Ok(NetCDFArray::Float(v.iter()
+                .map(|value| ((*value as f64 * scale) + offset) as f32)
+                .collect())),
+            _ => Ok(NetCDFArray::Double((0..values.len())
+                .filter_map(|index| values.get(index))
+                .map(|value| (value.to_f64() * scale) + offset)
+                .collect())),
+        }
+    }
+
+    /// Converts masked or unpacked data, the mask is kept.
+    pub fn convert_data(&self, data: &NetCDFMaskedData, to: &NetCDFUnit) -> Result<NetCDFMaskedData, NetCDFError> {
+        Ok(NetCDFMaskedData{
+            shape: data.shape.clone(),
+            values: self.convert_values(&data.values, to)?,
+            mask: data.mask.clone(),
+        })
+    }
+}
+
+/// None if an exponent does not fit into an i8.
+fn add_dimensions(a: &Dimensions, b: &Dimensions, exponent: i8) -> Option<Dimensions> {
+    let mut result = *a;
+
+    for (r, b) in result.iter_mut().zip(b.iter()) {
+        *r = r.checked_add(b.checked_mul(exponent)?)?;
+    }
+
+    Some(result)
+}
+
+/// Splits the text into terms, true if the term is a divisor.
+/// As in UDUNITS, '/' only applies to the next term: "kg/m s" is kg s / m.
+fn split_terms(text: &str) -> Vec<(String, bool)> {
+    let chars: Vec<char> = text.trim().chars().collect();
+    let mut result = Vec::new();
+    let mut term = String::new();
+    let mut divide = false;
+
+    let push = |term: &mut String, divide: &mut bool, result: &mut Vec<(String, bool)>| {
+        if !term.is_empty() {
+            result.push((term.clone(), *divide));
+            term.clear();
+            *divide = false;
+        }
+    };
+
+    for (index, c) in chars.iter().enumerate() {
+        // '.' is a decimal point between two digits
+        let decimal_point = *c == '.' && index > 0 && chars[index - 1].is_ascii_digit() &&
+            chars.get(index + 1).is_some_and(|next| next.is_ascii_digit());
+        // '*' is a multiplication, "**" an exponent
+        let exponent = *c == '*' && (chars.get(index + 1) == Some(&'*') || (index > 0 && chars[index - 1] == '*'));
+
+        match c {
+            '/' => {
+                push(&mut term, &mut divide, &mut result);
+                divide = true;
+            }
+            '.' | '*' | '·' if !decimal_point && !exponent => push(&mut term, &mut divide, &mut result),
+            c if c.is_whitespace() => push(&mut term, &mut divide, &mut result),
+            c => term.push(*c),
+        }
+    }
+
+    push(&mut term, &mut divide, &mut result);
+    result
+}
+
+/// Parses a number or a unit with an optional exponent.
+fn parse_term(term: &str) -> Option<(NetCDFUnit, i8)> {
+    if let Ok(number) = term.parse::<f64>() {
+        return Some((NetCDFUnit{factor: number, offset: 0.0, dimensions: DIMENSIONLESS}, 1))
+    }
+
+    let split = term.find(|c: char| c.is_ascii_digit() || c == '-' || c == '+' || c == '^' || c == '*')
+        .unwrap_or(term.len());
+    let (name, exponent) = term.split_at(split);
+    let exponent = exponent.trim_start_matches(['^', '*']);
+    let exponent: i8 = if exponent.is_empty() { 1 } else { exponent.parse().ok()? };
+
+    Some((lookup_unit(name)?, exponent))
+}
+
+fn lookup_unit(name: &str) -> Option<NetCDFUnit> {
+    // Prefixes are tried before plurals, so "ms" is a millisecond and not meters
+    lookup_name(name)
+        .or_else(|| lookup_prefixed(name))
+        .or_else(|| name.strip_suffix('s').and_then(|name| lookup_name(name).or_else(|| lookup_prefixed(name))))
+}
+
+fn lookup_prefixed(name: &str) -> Option<NetCDFUnit> {
+    PREFIXES.iter()
+        .filter_map(|(prefix, factor)| name.strip_prefix(prefix)
+            .and_then(lookup_name)
+            .map(|unit| (unit, factor)))
+        // Units with an offset can not have a prefix
+        .find(|(unit, _)| unit.offset == 0.0)
+        .map(|(unit, factor)| NetCDFUnit{factor: unit.factor * factor, ..unit})
+}
+
+fn lookup_name(name: &str) -> Option<NetCDFUnit> {
+    if DEGREES.contains(&name) {
+        return Some(NetCDFUnit{factor: PI / 180.0, offset: 0.0, dimensions: DIMENSIONLESS})
+    }
+
+    UNITS.iter()
+        .find(|(unit_name, _, _, _)| *unit_name == name)
+        .map(|(_, factor, offset, dimensions)| NetCDFUnit{factor: *factor, offset: *offset, dimensions: *dimensions})
+}
@@ -0,0 +1,721 @@
+// Rust modules
+use std::path::Path;
+use std::fs::{File, OpenOptions};
+use std::{io, io::BufWriter, io::Read, io::Seek, io::SeekFrom, io::Write};
+
+// External modules
+use log::{info, debug};
+use byteorder::{ByteOrder, BigEndian};
+
+// Internal modules
+use crate::netcdf::*;
+use crate::reader::{read_header, load_file};
+
+/// Offsets in version 1 files are signed 32 bit numbers (2 GiB limit).
+const MAX_OFFSET32: u64 = i32::MAX as u64;
+
+/// Options that control how a data set is written.
+#[derive(Debug, Clone, Default)]
+pub struct NetCDFWriteOptions {
+    /// Write numrecs as STREAMING instead of the actual number of records.
+    /// Readers then compute the number of records from the size of the file,
+    /// so the output does not need to be seekable.
+    pub streaming: bool,
+    /// Minimum free space after the header, so that attributes can be added later
+    /// without moving the data (h_minfree in nc__enddef).
+    pub h_minfree: u64,
+    /// Alignment of the start of the fixed size variables, 0 or 1 for no alignment (v_align).
+    pub v_align: u64,
+    /// Minimum free space after the fixed size variables (v_minfree).
+    pub v_minfree: u64,
+    /// Alignment of the start of the record variables, 0 or 1 for no alignment (r_align).
+    pub r_align: u64,
+}
+
+/// Lower bounds for the start of the fixed size and the record section.
+/// Used to keep the data of an existing file in place.
+#[derive(Debug, Default)]
+pub(crate) struct SectionBegin {
+    pub(crate) fixed: u64,
+    pub(crate) record: u64,
+}
+
+pub fn save_file<T: AsRef<Path>>(path: T, netcdf: &NetCDF) -> Result<(), NetCDFError> {
+    save_file_with_options(path, netcdf, &NetCDFWriteOptions::default())
+}
+
+pub fn save_file_with_options<T: AsRef<Path>>(path: T, netcdf: &NetCDF, options: &NetCDFWriteOptions) -> Result<(), NetCDFError> {
+    let file_path = path.as_ref();
+    info!("writer.rs, save_file_with_options, trying to create file: '{}'", file_path.display());
+    let file = File::create(file_path)?;
+    let mut buf_writer = BufWriter::new(file);
+    save_writer_with_options(&mut buf_writer, netcdf, options)?;
+    buf_writer.flush()?;
+    Ok(())
+}
+
+/// Writes a classic netCDF file. A version 1 (CDF01) data set is written as
+/// version 2 (CDF02) if the data section does not fit into 32 bit offsets.
+pub fn save_writer<T: Write>(writer: &mut T, netcdf: &NetCDF) -> Result<(), NetCDFError> {
+    save_writer_with_options(writer, netcdf, &NetCDFWriteOptions::default())
+}
+
+pub fn save_writer_with_options<T: Write>(writer: &mut T, netcdf: &NetCDF, options: &NetCDFWriteOptions) -> Result<(), NetCDFError> {
+    let header = &netcdf.header;
+
+    match header.version {
+        NetCDFVersion::CDF01 | NetCDFVersion::CDF02 | NetCDFVersion::CDF05 => {}
+        _ => return Err(NetCDFError::WriteVersionNotSupported)
+    }
+
+    check_data(netcdf)?;
+
+    let num_of_records = netcdf.data.recs.len() as u64;
+    let (version, layout) = plan_layout(header, num_of_records, options, &SectionBegin::default())?;
+    let numrecs = if options.streaming {
+        NetCDFStreaming::Streaming(num_of_records)
+    } else {
+        NetCDFStreaming::Normal(num_of_records)
+    };
+    let mut buffer = Vec::new();
+    write_header(&mut buffer, header, &version, &numrecs, &layout)?;
+    writer.write_all(&buffer)?;
+    write_data(writer, netcdf, &layout, buffer.len() as u64)?;
+
+    Ok(())
+}
+
+/// Rewrites a file in another classic format version, like "nccopy -k".
+pub fn convert_file<T: AsRef<Path>, U: AsRef<Path>>(input: T, output: U, version: NetCDFVersion) -> Result<(), NetCDFError> {
+    let netcdf = load_file(input)?;
+    let netcdf = convert_version(netcdf, version)?;
+    save_file(output, &netcdf)
+}
+
+/// Changes the format version of a data set and updates vsize and offsets of the variables.
+/// In contrast to save_file() a version 1 data set is never switched to version 2,
+/// everything that can not be represented in the new version is an InvalidConversion error.
+pub fn convert_version(mut netcdf: NetCDF, version: NetCDFVersion) -> Result<NetCDF, NetCDFError> {
+    info!("writer.rs, convert_version, from {:?} to {:?}", netcdf.header.version, version);
+    check_data(&netcdf)?;
+
+    let num_of_records = netcdf.data.recs.len() as u64;
+    let mut header = netcdf.header.clone();
+    header.version = version;
+    check_conversion(&header, num_of_records)?;
+
+    let (planned_version, _) = plan_layout(&header, num_of_records, &NetCDFWriteOptions::default(), &SectionBegin::default())?;
+
+    if planned_version != version {
+        return Err(NetCDFError::InvalidConversion(format!(
+            "the data section needs offsets larger than {} bytes, use version 2 (CDF02) or 5 (CDF05)", MAX_OFFSET32)))
+    }
+
+    update_layout(&mut header, num_of_records, &NetCDFWriteOptions::default(), &SectionBegin::default())?;
+    netcdf.header = header;
+
+    Ok(netcdf)
+}
+
+/// Checks the limits of the classic format versions 1 and 2:
+/// no CDF-5 types, 32 bit lengths and only the last fixed size and the
+/// last record variable may be larger than 4 GiB (2 GiB for version 1).
+fn check_conversion(header: &NetCDFHeader, num_of_records: u64) -> Result<(), NetCDFError> {
+    match header.version {
+        NetCDFVersion::CDF01 | NetCDFVersion::CDF02 => {}
+        NetCDFVersion::CDF05 => return Ok(()),
+        NetCDFVersion::HDF5 => return Err(NetCDFError::InvalidConversion("only the classic format versions 1 (CDF01), 2 (CDF02) and 5 (CDF05) can be written".to_string())),
+    }
+
+    for attribute in header.att_list.iter() {
+        check_attribute_conversion(header, "global attribute", attribute)?;
+    }
+
+    for variable in header.var_list.iter() {
+        if variable.nc_type.needs_cdf5() {
+            return Err(NetCDFError::InvalidConversion(format!("variable '{}' has type {}, which needs version 5 (CDF05), got {:?}",
+                variable.name, variable.nc_type.name(), header.version)))
+        }
+
+        for attribute in variable.att_list.iter() {
+            check_attribute_conversion(header, &format!("attribute of variable '{}'", variable.name), attribute)?;
+        }
+    }
+
+    // STREAMING (2^32 - 1) can not be used as number of records
+    if num_of_records >= u32::MAX as u64 {
+        return Err(NetCDFError::InvalidConversion(format!("{} records do not fit into 32 bits", num_of_records)))
+    }
+
+    for dimension in header.dim_list.iter() {
+        if dimension.length > u32::MAX as u64 {
+            return Err(NetCDFError::InvalidConversion(format!("the length {} of dimension '{}' does not fit into 32 bits",
+                dimension.length, dimension.name)))
+        }
+    }
+
+    check_large_variables(header, &header.version).map_err(NetCDFError::InvalidConversion)
+}
+
+/// In versions 1 and 2 only the last fixed size and the last record variable may be
+/// larger than 4 GiB (2 GiB for version 1), because the vsize of all the others is
+/// needed to find the data. Returns the reason if this is not the case.
+fn check_large_variables(header: &NetCDFHeader, version: &NetCDFVersion) -> Result<(), String> {
+    let max_vsize = match version {
+        NetCDFVersion::CDF01 => MAX_OFFSET32 - 3,
+        NetCDFVersion::CDF02 => u32::MAX as u64 - 3,
+        _ => return Ok(()),
+    };
+
+    let last_fixed = header.var_list.iter().rposition(|variable| !header.is_record_variable(variable));
+    let last_record = header.var_list.iter().rposition(|variable| header.is_record_variable(variable));
+
+    for (index, variable) in header.var_list.iter().enumerate() {
+        let vsize = padded_size(header.data_size(variable));
+
+        if vsize > max_vsize && Some(index) != last_fixed && Some(index) != last_record {
+            return Err(format!(
+                "variable '{}' has {} bytes, only the last fixed size and the last record variable can be larger than {} bytes in version {:?}",
+                variable.name, vsize, max_vsize, version))
+        }
+    }
+
+    Ok(())
+}
+
+fn check_attribute_conversion(header: &NetCDFHeader, location: &str, attribute: &NetCDFAttribute) -> Result<(), NetCDFError> {
+    let nc_type = attribute.values.nc_type();
+
+    if nc_type.needs_cdf5() {
+        return Err(NetCDFError::InvalidConversion(format!("{} '{}' has type {}, which needs version 5 (CDF05), got {:?}",
+            location, attribute.name, nc_type.name(), header.version)))
+    }
+
+    if attribute.values.len() as u64 > u32::MAX as u64 {
+        return Err(NetCDFError::InvalidConversion(format!("{} '{}' has too many values", location, attribute.name)))
+    }
+
+    Ok(())
+}
+
+/// Opens an existing file to append records along the unlimited dimension.
+pub fn open_append<T: AsRef<Path>>(path: T) -> Result<NetCDFAppender<File>, NetCDFError> {
+    let file_path = path.as_ref();
+    info!("writer.rs, open_append, trying to open file: '{}'", file_path.display());
+    let file = OpenOptions::new().read(true).write(true).open(file_path)?;
+    open_appender(file)
+}
+
+pub fn open_appender<F: Read + Write + Seek>(mut file: F) -> Result<NetCDFAppender<F>, NetCDFError> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut header = read_header(&mut file)?;
+
+    if let NetCDFStreaming::Streaming(_) = header.numrecs {
+        let file_size = file.seek(SeekFrom::End(0))?;
+        header.resolve_streaming(file_size);
+    }
+
+    if !header.var_list.iter().any(|variable| header.is_record_variable(variable)) {
+        return Err(NetCDFError::InvalidData("file has no record variables".to_string()))
+    }
+
+    Ok(NetCDFAppender{header, file})
+}
+
+/// Appends records to an existing file without rewriting it:
+/// the new records are written at the end of the record section
+/// and numrecs is updated in the header.
+pub struct NetCDFAppender<F: Read + Write + Seek> {
+    header: NetCDFHeader,
+    file: F,
+}
+
+impl<F: Read + Write + Seek> NetCDFAppender<F> {
+    pub fn header(&self) -> &NetCDFHeader {
+        &self.header
+    }
+
+    /// Appends one record, values contains one slab for each record variable
+    /// in the order of the var_list.
+    pub fn append_record(&mut self, values: &[NetCDFArray]) -> Result<(), NetCDFError> {
+        let header = &self.header;
+        let record_variables: Vec<&NetCDFVariable> = header.var_list.iter()
+            .filter(|variable| header.is_record_variable(variable))
+            .collect();
+
+        if values.len() != record_variables.len() {
+            return Err(NetCDFError::InvalidData(format!("expected {} record variables, got {}",
+                record_variables.len(), values.len())))
+        }
+
+        for (variable, var_slab) in record_variables.iter().zip(values.iter()) {
+            check_values(header, variable, var_slab)?;
+        }
+
+        let num_of_records = header.num_of_records();
+        let max_records = match header.version {
+            NetCDFVersion::CDF05 => u64::MAX - 1,
+            _ => (u32::MAX - 1) as u64,
+        };
+
+        if num_of_records >= max_records {
+            return Err(NetCDFError::InvalidData(format!("number of records can not exceed {}", max_records)))
+        }
+
+        let record_size = header.record_size();
+        let padding = header.pads_records();
+        debug!("NetCDFAppender::append_record, record: {}, recsize: {}", num_of_records, record_size);
+
+        for (variable, var_slab) in record_variables.iter().zip(values.iter()) {
+            self.file.seek(SeekFrom::Start(variable.offset.position() + (num_of_records * record_size)))?;
+            let buffer = encode_values(var_slab);
+            self.file.write_all(&buffer)?;
+
+            if padding {
+                write_fill_padding(&mut self.file, variable, buffer.len())?;
+            }
+        }
+
+        // Update numrecs only after the data has been written,
+        // a streaming file stays streaming.
+        match header.numrecs {
+            NetCDFStreaming::Streaming(_) => {
+                self.file.flush()?;
+                self.header.numrecs = NetCDFStreaming::Streaming(num_of_records + 1);
+            }
+            NetCDFStreaming::Normal(_) => {
+                let version = header.version;
+                self.file.seek(SeekFrom::Start(4))?;
+                write_number_of_elements(&mut self.file, &version, num_of_records + 1)?;
+                self.file.flush()?;
+                self.header.numrecs = NetCDFStreaming::Normal(num_of_records + 1);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Sets version, vsize and offset of all variables in the header to the values
+/// they will have in the written file.
+pub(crate) fn update_layout(header: &mut NetCDFHeader, num_of_records: u64, options: &NetCDFWriteOptions,
+        section_begin: &SectionBegin) -> Result<(), NetCDFError> {
+    let (version, layout) = plan_layout(header, num_of_records, options, section_begin)?;
+    header.version = version;
+
+    for (variable, var_layout) in header.var_list.iter_mut().zip(layout.iter()) {
+        variable.vsize = var_layout.vsize;
+        variable.offset = match header.version {
+            NetCDFVersion::CDF01 => NetCDFOffset::Pos32(var_layout.begin as u32),
+            _ => NetCDFOffset::Pos64(var_layout.begin),
+        };
+    }
+
+    Ok(())
+}
+
+/// Chooses the version to write and computes the layout of the data section for it.
+fn plan_layout(header: &NetCDFHeader, num_of_records: u64, options: &NetCDFWriteOptions, section_begin: &SectionBegin)
+        -> Result<(NetCDFVersion, Vec<VarLayout>), NetCDFError> {
+    let layout = compute_layout_for_version(header, &header.version, num_of_records, options, section_begin)?;
+
+    let (version, layout) = if header.version == NetCDFVersion::CDF01 && layout.iter().any(|var_layout| var_layout.begin > MAX_OFFSET32) {
+        info!("writer.rs, plan_layout, data section too large for version 1, switching to version 2 (CDF02)");
+        let layout = compute_layout_for_version(header, &NetCDFVersion::CDF02, num_of_records, options, section_begin)?;
+        (NetCDFVersion::CDF02, layout)
+    } else {
+        (header.version, layout)
+    };
+
+    check_large_variables(header, &version).map_err(NetCDFError::InvalidData)?;
+
+    Ok((version, layout))
+}
+
+fn compute_layout_for_version(header: &NetCDFHeader, version: &NetCDFVersion, num_of_records: u64,
+        options: &NetCDFWriteOptions, section_begin: &SectionBegin) -> Result<Vec<VarLayout>, NetCDFError> {
+    // The size of the header does not depend on the values of the offsets,
+    // so write it once with dummy offsets to find out where the data starts.
+    let dummy_layout: Vec<VarLayout> = compute_layout(header, 0, options, &SectionBegin::default()).into_iter()
+        .map(|var_layout| VarLayout{begin: 0, ..var_layout})
+        .collect();
+    let mut buffer = Vec::new();
+    write_header(&mut buffer, header, version, &NetCDFStreaming::Normal(num_of_records), &dummy_layout)?;
+    let header_size = buffer.len() as u64;
+    debug!("compute_layout_for_version, version: {:?}, header size: {}", version, header_size);
+
+    Ok(compute_layout(header, header_size, options, section_begin))
+}
+
+/// vsize and begin of each variable in the var_list.
+struct VarLayout {
+    vsize: u64,
+    begin: u64,
+}
+
+fn compute_layout(header: &NetCDFHeader, header_size: u64, options: &NetCDFWriteOptions, section_begin: &SectionBegin) -> Vec<VarLayout> {
+    let mut result: Vec<VarLayout> = header.var_list.iter().map(|variable| {
+        VarLayout{vsize: padded_size(header.data_size(variable)), begin: 0}
+    }).collect();
+
+    // Non-record variables first, then the record variables
+    let mut position = align(header_size + options.h_minfree, options.v_align).max(section_begin.fixed);
+
+    for (variable, var_layout) in header.var_list.iter().zip(result.iter_mut()) {
+        if !header.is_record_variable(variable) {
+            var_layout.begin = position;
+            position += var_layout.vsize;
+        }
+    }
+
+    position = align(position + options.v_minfree, options.r_align).max(section_begin.record);
+    let pads_records = header.pads_records();
+
+    for (variable, var_layout) in header.var_list.iter().zip(result.iter_mut()) {
+        if header.is_record_variable(variable) {
+            var_layout.begin = position;
+
+            if pads_records {
+                position += var_layout.vsize;
+            } else {
+                position += header.data_size(variable);
+            }
+        }
+    }
+
+    result
+}
+
+fn align(position: u64, alignment: u64) -> u64 {
+    if alignment > 1 {
+        position.div_ceil(alignment) * alignment
+    } else {
+        position
+    }
+}
+
+/// Make sure that the data matches the variables in the header.
+fn check_data(netcdf: &NetCDF) -> Result<(), NetCDFError> {
+    let header = &netcdf.header;
+    let non_record_variables: Vec<&NetCDFVariable> = header.var_list.iter()
+        .filter(|variable| !header.is_record_variable(variable))
+        .collect();
+    let record_variables: Vec<&NetCDFVariable> = header.var_list.iter()
+        .filter(|variable| header.is_record_variable(variable))
+        .collect();
+
+    if netcdf.data.non_recs.len() != non_record_variables.len() {
+        return Err(NetCDFError::InvalidData(format!("expected data for {} non-record variables, got {}",
+            non_record_variables.len(), netcdf.data.non_recs.len())))
+    }
+
+    for (variable, var_data) in non_record_variables.iter().zip(netcdf.data.non_recs.iter()) {
+        check_values(header, variable, &var_data.values)?;
+    }
+
+    for attribute in header.att_list.iter().chain(header.var_list.iter().flat_map(|variable| variable.att_list.iter())) {
+        check_type(header, &attribute.name, attribute.values.nc_type())?;
+    }
+
+    for variable in header.var_list.iter() {
+        check_type(header, &variable.name, variable.nc_type)?;
+    }
+
+    for record in netcdf.data.recs.iter() {
+        if record.record.len() != record_variables.len() {
+            return Err(NetCDFError::InvalidData(format!("expected {} record variables in each record, got {}",
+                record_variables.len(), record.record.len())))
+        }
+
+        for (variable, var_slab) in record_variables.iter().zip(record.record.iter()) {
+            check_values(header, variable, &var_slab.varslab)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_type(header: &NetCDFHeader, name: &str, nc_type: NetCDFType) -> Result<(), NetCDFError> {
+    if nc_type.needs_cdf5() && header.version != NetCDFVersion::CDF05 {
+        return Err(NetCDFError::InvalidData(format!("'{}' has type {:?} which needs version 5 (CDF05), got {:?}",
+            name, nc_type, header.version)))
+    }
+
+    Ok(())
+}
+
+fn check_values(header: &NetCDFHeader, variable: &NetCDFVariable, values: &NetCDFArray) -> Result<(), NetCDFError> {
+    if values.nc_type() != variable.nc_type {
+        return Err(NetCDFError::InvalidData(format!("variable '{}' has type {:?}, got values of type {:?}",
+            variable.name, variable.nc_type, values.nc_type())))
+    }
+
+    let nvals = header.num_of_values(variable);
+
+    if values.len() != nvals {
+        return Err(NetCDFError::InvalidData(format!("variable '{}' needs {} values, got {}",
+            variable.name, nvals, values.len())))
+    }
+
+    Ok(())
+}
+
+/// Writes the header with the version, numrecs, vsize and offsets that are
+/// stored in it, see update_layout().
+pub(crate) fn write_current_header<T: Write>(writer: &mut T, header: &NetCDFHeader) -> Result<(), NetCDFError> {
+    let layout: Vec<VarLayout> = header.var_list.iter()
+        .map(|variable| VarLayout{vsize: variable.vsize, begin: variable.offset.position()})
+        .collect();
+    write_header(writer, header, &header.version, &header.numrecs, &layout)
+}
+
+fn write_header<T: Write>(writer: &mut T, header: &NetCDFHeader, version: &NetCDFVersion, numrecs: &NetCDFStreaming, layout: &[VarLayout]) -> Result<(), NetCDFError> {
+    write_version(writer, version)?;
+    write_numrecs(writer, version, numrecs)?;
+    write_dim_list(writer, version, &header.dim_list)?;
+    write_att_list(writer, version, &header.att_list)?;
+    write_var_list(writer, version, &header.var_list, layout)?;
+    Ok(())
+}
+
+fn write_numrecs<T: Write>(writer: &mut T, version: &NetCDFVersion, numrecs: &NetCDFStreaming) -> Result<(), NetCDFError> {
+    match (numrecs, version) {
+        (NetCDFStreaming::Streaming(_), NetCDFVersion::CDF05) => writer.write_all(&STREAMING64)?,
+        (NetCDFStreaming::Streaming(_), _) => writer.write_all(&STREAMING)?,
+        (NetCDFStreaming::Normal(num_of_records), _) => write_number_of_elements(writer, version, *num_of_records)?,
+    }
+    Ok(())
+}
+
+fn write_version<T: Write>(writer: &mut T, version: &NetCDFVersion) -> Result<(), NetCDFError> {
+    let buffer = match version {
+        NetCDFVersion::CDF01 => VERSION1,
+        NetCDFVersion::CDF02 => VERSION2,
+        NetCDFVersion::CDF05 => VERSION5,
+        NetCDFVersion::HDF5 => return Err(NetCDFError::HDF5NotSupportetYet),
+    };
+
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+fn write_dim_list<T: Write>(writer: &mut T, version: &NetCDFVersion, dim_list: &[NetCDFDimension]) -> Result<(), NetCDFError> {
+    if dim_list.is_empty() {
+        return write_absent(writer, version)
+    }
+
+    writer.write_all(&NC_DIMENSION)?;
+    write_number_of_elements(writer, version, dim_list.len() as u64)?;
+
+    for dimension in dim_list.iter() {
+        write_name(writer, version, &dimension.name)?;
+        write_number_of_elements(writer, version, dimension.length)?;
+    }
+
+    Ok(())
+}
+
+fn write_att_list<T: Write>(writer: &mut T, version: &NetCDFVersion, att_list: &[NetCDFAttribute]) -> Result<(), NetCDFError> {
+    if att_list.is_empty() {
+        return write_absent(writer, version)
+    }
+
+    writer.write_all(&NC_ATTRIBUTE)?;
+    write_number_of_elements(writer, version, att_list.len() as u64)?;
+
+    for attribute in att_list.iter() {
+        write_name(writer, version, &attribute.name)?;
+        write_nc_type(writer, attribute.values.nc_type())?;
+        write_number_of_elements(writer, version, attribute.values.len() as u64)?;
+        let buffer = encode_values(&attribute.values);
+        writer.write_all(&buffer)?;
+        // Padding in the header is always done with zero bytes
+        write_zero_padding(writer, buffer.len())?;
+    }
+
+    Ok(())
+}
+
+fn write_var_list<T: Write>(writer: &mut T, version: &NetCDFVersion, var_list: &[NetCDFVariable], layout: &[VarLayout]) -> Result<(), NetCDFError> {
+    if var_list.is_empty() {
+        return write_absent(writer, version)
+    }
+
+    writer.write_all(&NC_VARIABLE)?;
+    write_number_of_elements(writer, version, var_list.len() as u64)?;
+
+    for (variable, var_layout) in var_list.iter().zip(layout.iter()) {
+        write_name(writer, version, &variable.name)?;
+        write_number_of_elements(writer, version, variable.dimid.len() as u64)?;
+
+        for dim_id in variable.dimid.iter() {
+            write_number_of_elements(writer, version, *dim_id as u64)?;
+        }
+
+        write_att_list(writer, version, &variable.att_list)?;
+        write_nc_type(writer, variable.nc_type)?;
+        write_vsize(writer, version, var_layout.vsize)?;
+        write_offset(writer, version, var_layout.begin)?;
+    }
+
+    Ok(())
+}
+
+fn write_absent<T: Write>(writer: &mut T, version: &NetCDFVersion) -> Result<(), NetCDFError> {
+    writer.write_all(&ZERO)?;
+    write_number_of_elements(writer, version, 0)
+}
+
+fn write_name<T: Write>(writer: &mut T, version: &NetCDFVersion, name: &str) -> Result<(), NetCDFError> {
+    let buffer = name.as_bytes();
+    write_number_of_elements(writer, version, buffer.len() as u64)?;
+    writer.write_all(buffer)?;
+    write_zero_padding(writer, buffer.len())
+}
+
+fn write_zero_padding<T: Write>(writer: &mut T, size_in_bytes: usize) -> Result<(), NetCDFError> {
+    let padding = padded_size(size_in_bytes as u64) as usize - size_in_bytes;
+    writer.write_all(&ZERO[..padding])?;
+    Ok(())
+}
+
+/// Fills free space in front of a data section.
+fn write_zeros<T: Write>(writer: &mut T, size_in_bytes: u64) -> Result<(), NetCDFError> {
+    io::copy(&mut io::repeat(0).take(size_in_bytes), writer)?;
+    Ok(())
+}
+
+/// Non-negative numbers are 32 bit in CDF-1 and CDF-2 and 64 bit in CDF-5.
+fn write_number_of_elements<T: Write>(writer: &mut T, version: &NetCDFVersion, value: u64) -> Result<(), NetCDFError> {
+    match version {
+        NetCDFVersion::CDF05 => writer.write_all(&value.to_be_bytes())?,
+        _ => {
+            if value > u32::MAX as u64 {
+                return Err(NetCDFError::InvalidData(format!("value {} does not fit into 32 bits", value)))
+            }
+
+            writer.write_all(&(value as u32).to_be_bytes())?
+        }
+    }
+
+    Ok(())
+}
+
+/// A vsize that does not fit into 32 bits is written as 2^32 - 1,
+/// readers have to compute the real size from the dimensions.
+fn write_vsize<T: Write>(writer: &mut T, version: &NetCDFVersion, vsize: u64) -> Result<(), NetCDFError> {
+    match version {
+        NetCDFVersion::CDF05 => write_number_of_elements(writer, version, vsize),
+        _ => write_number_of_elements(writer, version, vsize.min(u32::MAX as u64)),
+    }
+}
+
+fn write_offset<T: Write>(writer: &mut T, version: &NetCDFVersion, offset: u64) -> Result<(), NetCDFError> {
+    match version {
+        NetCDFVersion::CDF01 => {
+            if offset > MAX_OFFSET32 {
+                return Err(NetCDFError::InvalidData(format!("offset {} does not fit into 32 bits", offset)))
+            }
+
+            writer.write_all(&(offset as u32).to_be_bytes())?;
+        }
+        NetCDFVersion::CDF02 | NetCDFVersion::CDF05 => {
+            writer.write_all(&offset.to_be_bytes())?;
+        }
+        _ => return Err(NetCDFError::UnknownOffsetVersion)
+    }
+
+    Ok(())
+}
+
+fn write_nc_type<T: Write>(writer: &mut T, nc_type: NetCDFType) -> Result<(), NetCDFError> {
+    let buffer = match nc_type {
+        NetCDFType::NCByte => NC_BYTE,
+        NetCDFType::NCChar => NC_CHAR,
+        NetCDFType::NCShort => NC_SHORT,
+        NetCDFType::NCInt => NC_INT,
+        NetCDFType::NCFloat => NC_FLOAT,
+        NetCDFType::NCDouble => NC_DOUBLE,
+        NetCDFType::NCUByte => NC_UBYTE,
+        NetCDFType::NCUShort => NC_USHORT,
+        NetCDFType::NCUInt => NC_UINT,
+        NetCDFType::NCInt64 => NC_INT64,
+        NetCDFType::NCUInt64 => NC_UINT64,
+    };
+
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+/// Converts typed values into the big endian bytes of the file.
+pub(crate) fn encode_values(values: &NetCDFArray) -> Vec<u8> {
+    let mut buffer = vec![0; values.len() * values.nc_type().size_in_bytes()];
+
+    match values {
+        NetCDFArray::Byte(v) => {
+            for (b, x) in buffer.iter_mut().zip(v.iter()) {
+                *b = *x as u8;
+            }
+        }
+        NetCDFArray::Char(v) => buffer.copy_from_slice(v),
+        NetCDFArray::Short(v) => BigEndian::write_i16_into(v, &mut buffer),
+        NetCDFArray::Int(v) => BigEndian::write_i32_into(v, &mut buffer),
+        NetCDFArray::Float(v) => BigEndian::write_f32_into(v, &mut buffer),
+        NetCDFArray::Double(v) => BigEndian::write_f64_into(v, &mut buffer),
+        NetCDFArray::UByte(v) => buffer.copy_from_slice(v),
+        NetCDFArray::UShort(v) => BigEndian::write_u16_into(v, &mut buffer),
+        NetCDFArray::UInt(v) => BigEndian::write_u32_into(v, &mut buffer),
+        NetCDFArray::Int64(v) => BigEndian::write_i64_into(v, &mut buffer),
+        NetCDFArray::UInt64(v) => BigEndian::write_u64_into(v, &mut buffer),
+    }
+
+    buffer
+}
+
+/// In the data section the values are padded with the fill value of the variable.
+pub(crate) fn write_fill_padding<T: Write>(writer: &mut T, variable: &NetCDFVariable, size_in_bytes: usize) -> Result<(), NetCDFError> {
+    let padding = padded_size(size_in_bytes as u64) as usize - size_in_bytes;
+
+    // Only the types with less than 4 bytes need padding
+    if padding > 0 {
+        let fill = encode_values(&NetCDFArray::repeat(&variable.fill_value(), padding));
+        writer.write_all(&fill[..padding])?;
+    }
+
+    Ok(())
+}
+
+fn write_data<T: Write>(writer: &mut T, netcdf: &NetCDF, layout: &[VarLayout], header_size: u64) -> Result<(), NetCDFError> {
+    let header = &netcdf.header;
+    let mut position = header_size;
+    let (records, non_records): (Vec<_>, Vec<_>) = header.var_list.iter().zip(layout.iter())
+        .partition(|(variable, _)| header.is_record_variable(variable));
+
+    // The space in front of each section is filled with zeros
+    for ((variable, var_layout), var_data) in non_records.iter().zip(netcdf.data.non_recs.iter()) {
+        write_zeros(writer, var_layout.begin - position)?;
+        let buffer = encode_values(&var_data.values);
+        writer.write_all(&buffer)?;
+        write_fill_padding(writer, variable, buffer.len())?;
+        position = var_layout.begin + padded_size(buffer.len() as u64);
+    }
+
+    if let Some(record_begin) = records.iter().map(|(_, var_layout)| var_layout.begin).min() {
+        write_zeros(writer, record_begin - position)?;
+    }
+
+    let padding = header.pads_records();
+
+    for record in netcdf.data.recs.iter() {
+        for ((variable, _), var_slab) in records.iter().zip(record.record.iter()) {
+            let buffer = encode_values(&var_slab.varslab);
+            writer.write_all(&buffer)?;
+
+            if padding {
+                write_fill_padding(writer, variable, buffer.len())?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::fs;
+
+use netcdfrs::prelude::*;
+use netcdfrs::prelude::NetCDFDimensionLength::*;
+
+#[test]
+fn build_record1() {
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", Unlimited)
+        .add_dimension("lat", Fixed(2))
+        .add_variable("lat", NetCDFType::NCFloat, &["lat"])
+        .add_variable("times", NetCDFType::NCShort, &["time"])
+        .add_variable("temps", NetCDFType::NCFloat, &["time", "lat"])
+        .set_variable_data("lat", NetCDFArray::Float(vec![10.5, 20.5]))
+        .set_variable_data("times", NetCDFArray::Short(vec![1, 2, 3]))
+        .set_variable_data("temps", NetCDFArray::Float(vec![280.0, 281.5, 282.0, 283.5, 284.0, 285.5]))
+        .build()
+        .unwrap();
+
+    assert_eq!(netcdf.num_of_records(), 3);
+
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &netcdf).unwrap();
+    assert_eq!(buffer, fs::read("tests/version1/record1.nc").unwrap());
+}
+
+#[test]
+fn build_fill_values() {
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", Unlimited)
+        .add_dimension("x", Fixed(2))
+        .add_attribute("title", NetCDFArray::Char(b"fill".to_vec()))
+        .add_variable("a", NetCDFType::NCShort, &["time"])
+        .add_variable("b", NetCDFType::NCFloat, &["time", "x"])
+        .add_variable("c", NetCDFType::NCDouble, &["x"])
+        .add_variable_attribute("c", "units", NetCDFArray::Char(b"m".to_vec()))
+        .set_variable_data("a", NetCDFArray::Short(vec![1, 2]))
+        .set_variable_data("b", NetCDFArray::Float(vec![1.0, 2.0]))
+        .build()
+        .unwrap();
+
+    assert_eq!(netcdf.num_of_records(), 2);
+    assert_eq!(netcdf.variable_data("b").unwrap().values,
+        NetCDFArray::Float(vec![1.0, 2.0, 9.96921e36, 9.96921e36]));
+    assert_eq!(netcdf.variable_data("c").unwrap().values,
+        NetCDFArray::Double(vec![9.969209968386869e36, 9.969209968386869e36]));
+    assert_eq!(netcdf.list_of_variables()[2].att_list[0].values.as_string(), Some("m".to_string()));
+
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &netcdf).unwrap();
+    let netcdf = load_reader(&mut buffer.as_slice()).unwrap();
+    assert_eq!(netcdf.variable_data("a").unwrap().values, NetCDFArray::Short(vec![1, 2]));
+    assert_eq!(netcdf.list_of_attributes()[0].values.as_string(), Some("fill".to_string()));
+}
+
+#[test]
+fn build_fill_value_attribute() {
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(3))
+        .add_variable("a", NetCDFType::NCShort, &["x"])
+        .add_variable_attribute("a", "_FillValue", NetCDFArray::Short(vec![-1]))
+        .add_variable("b", NetCDFType::NCShort, &["x"])
+        .set_variable_data("b", NetCDFArray::Short(vec![1, -32767, 3]))
+        .build()
+        .unwrap();
+
+    assert_eq!(netcdf.variable_data("a").unwrap().values, NetCDFArray::Short(vec![-1, -1, -1]));
+
+    let a = &netcdf.list_of_variables()[0];
+    let b = &netcdf.list_of_variables()[1];
+    assert_eq!(a.fill_value(), NetCDFValue::Short(-1));
+    assert_eq!(b.fill_value(), NetCDFValue::Short(-32767));
+
+    let values = netcdf.variable_data("b").unwrap().values;
+    let fill: Vec<bool> = (0..values.len()).map(|i| b.is_fill_value(&values.get(i).unwrap())).collect();
+    assert_eq!(fill, vec![false, true, false]);
+
+    // The padding uses the fill value of the variable, too
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &netcdf).unwrap();
+    let end = buffer.len();
+    assert_eq!(&buffer[end - 16..], &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 1, 0x80, 1, 0, 3, 0x80, 1]);
+
+    let result = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(3))
+        .add_variable("a", NetCDFType::NCShort, &["x"])
+        .add_variable_attribute("a", "_FillValue", NetCDFArray::Int(vec![-1]))
+        .build();
+    assert!(result.is_err());
+}
+
+#[test]
+fn build_errors() {
+    let builder = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(2))
+        .add_variable("a", NetCDFType::NCShort, &["y"]);
+    assert!(builder.build().is_err());
+
+    let builder = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(2))
+        .add_dimension("x", Fixed(3));
+    assert!(builder.build().is_err());
+
+    let builder = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x/y", Fixed(2));
+    assert!(builder.build().is_err());
+
+    let builder = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", Unlimited)
+        .add_dimension("x", Fixed(2))
+        .add_variable("a", NetCDFType::NCShort, &["x", "time"]);
+    assert!(builder.build().is_err());
+
+    let builder = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(2))
+        .add_variable("a", NetCDFType::NCUInt, &["x"]);
+    assert!(builder.build().is_err());
+
+    let builder = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(2))
+        .add_variable("a", NetCDFType::NCInt, &["x"])
+        .set_variable_data("a", NetCDFArray::Int(vec![1, 2, 3]));
+    assert!(builder.build().is_err());
+
+    let builder = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_variable_attribute("a", "units", NetCDFArray::Char(b"m".to_vec()));
+    assert!(builder.build().is_err());
+}
@@ -0,0 +1,66 @@
+use netcdfrs::prelude::*;
+
+#[test]
+fn cdf5() {
+    let data = load_file("tests/version5/cdf5.nc").unwrap();
+
+    assert_eq!(data.num_of_records(), 2);
+    assert_eq!(data.num_of_dimensions(), 2);
+    assert_eq!(data.num_of_attributes(), 1);
+    assert_eq!(data.num_of_variables(), 6);
+    assert_eq!(data.list_of_attributes()[0].values.as_string(), Some("cdf5".to_string()));
+
+    let ub = &data.list_of_variables()[0];
+    assert_eq!(ub.nc_type, NetCDFType::NCUByte);
+    assert_eq!(ub.att_list[0].values, NetCDFArray::UByte(vec![250]));
+
+    assert_eq!(data.variable_data("ub").unwrap().values, NetCDFArray::UByte(vec![1, 2, 255]));
+    assert_eq!(data.variable_data("us").unwrap().values, NetCDFArray::UShort(vec![1, 2, 65535]));
+    assert_eq!(data.variable_data("ui").unwrap().values, NetCDFArray::UInt(vec![1, 2, 4294967295]));
+    assert_eq!(data.variable_data("i64").unwrap().values, NetCDFArray::Int64(vec![-1, 1 << 40, 3]));
+    assert_eq!(data.variable_data("u64").unwrap().values, NetCDFArray::UInt64(vec![(1 << 63) + 5, 0, 1]));
+    assert_eq!(data.variable_data("rec").unwrap().values, NetCDFArray::Int64(vec![10, 20]));
+}
+
+#[test]
+fn cdf5_open() {
+    let mut reader = open_file("tests/version5/cdf5.nc").unwrap();
+
+    let u64 = reader.read_slice("u64", &[1], &[2], None).unwrap();
+    assert_eq!(u64.values.as_u64_slice(), Some(&[0, 1][..]));
+
+    let rec = reader.variable_data("rec").unwrap();
+    assert_eq!(rec.shape, vec![2]);
+    assert_eq!(rec.values.as_i64_slice(), Some(&[10, 20][..]));
+}
+
+/// A header with one record that claims a lot more data than the file has.
+fn truncated_record(nc_type: NetCDFType, length: u64) -> Vec<u8> {
+    let data = NetCDFBuilder::new(NetCDFVersion::CDF05)
+        .add_dimension("time", NetCDFDimensionLength::Unlimited)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(12345))
+        .add_variable("a", nc_type, &["time", "x"])
+        .build()
+        .unwrap();
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &data).unwrap();
+
+    buffer[4..12].copy_from_slice(&1u64.to_be_bytes());
+    let position = buffer.windows(8).position(|bytes| bytes == 12345u64.to_be_bytes()).unwrap();
+    buffer[position..position + 8].copy_from_slice(&length.to_be_bytes());
+    buffer.extend_from_slice(&[1, 2, 3, 4]);
+    buffer
+}
+
+#[test]
+fn cdf5_truncated() {
+    let buffer = truncated_record(NetCDFType::NCByte, 1 << 40);
+    assert!(matches!(load_reader(&mut &buffer[..]), Err(NetCDFError::IOError(_))));
+
+    let mut reader = open_reader(std::io::Cursor::new(&buffer)).unwrap();
+    assert!(matches!(reader.variable_data("a"), Err(NetCDFError::IOError(_))));
+
+    // The size in bytes does not fit into an usize
+    let buffer = truncated_record(NetCDFType::NCInt64, 1 << 62);
+    assert!(matches!(load_reader(&mut &buffer[..]), Err(NetCDFError::InvalidData(_))));
+}
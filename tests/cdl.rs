@@ -0,0 +1,208 @@
+use netcdfrs::prelude::*;
+use netcdfrs::prelude::NetCDFDimensionLength::*;
+
+#[test]
+fn cdl_record1() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+
+    assert_eq!(data.to_cdl("record1").unwrap(), "\
+netcdf record1 {
+dimensions:
+\ttime = UNLIMITED ; // (3 currently)
+\tlat = 2 ;
+variables:
+\tfloat lat(lat) ;
+\tshort times(time) ;
+\tfloat temps(time, lat) ;
+data:
+
+ lat = 10.5, 20.5 ;
+
+ times = 1, 2, 3 ;
+
+ temps =
+  280, 281.5,
+  282, 283.5,
+  284, 285.5 ;
+}
+");
+}
+
+#[test]
+fn cdl_options() {
+    let data = load_file("tests/version1/small2.nc").unwrap();
+
+    let mut output = Vec::new();
+    let options = NetCDFCdlOptions{header_only: true, ..NetCDFCdlOptions::default()};
+    write_cdl_with_options(&mut output, "small2", &data, &options).unwrap();
+    let header = String::from_utf8(output).unwrap();
+    assert!(header.ends_with("\tshort temps(temp) ;\n}\n"));
+
+    let mut output = Vec::new();
+    write_cdl_header(&mut output, "small2", data.header()).unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), header);
+
+    let mut output = Vec::new();
+    let options = NetCDFCdlOptions{variables: Some(vec!["temps".to_string()]), ..NetCDFCdlOptions::default()};
+    write_cdl_with_options(&mut output, "small2", &data, &options).unwrap();
+    assert!(String::from_utf8(output.clone()).unwrap().ends_with("data:\n\n temps = 30, 32, 34, 36, 40 ;\n}\n"));
+
+    let mut reader = open_file("tests/version1/small2.nc").unwrap();
+    let mut reader_output = Vec::new();
+    write_cdl_reader(&mut reader_output, "small2", &mut reader, &options).unwrap();
+    assert_eq!(reader_output, output);
+
+    let options = NetCDFCdlOptions{variables: Some(vec!["unknown".to_string()]), ..NetCDFCdlOptions::default()};
+    assert!(write_cdl_with_options(&mut Vec::new(), "small2", &data, &options).is_err());
+    assert!(write_cdl_reader(&mut Vec::new(), "small2", &mut reader, &options).is_err());
+}
+
+#[test]
+fn cdl_attributes_and_data() {
+    let data = NetCDFBuilder::new(NetCDFVersion::CDF05)
+        .add_attribute("title", NetCDFArray::Char(b"a \"quoted\"\ttext\nline2".to_vec()))
+        .add_attribute("f", NetCDFArray::Float(vec![1.0, 0.1, 1e20, -3.5e-7, f32::NAN]))
+        .add_attribute("d", NetCDFArray::Double(vec![1.0, 0.1, 123456789012345678.0]))
+        .add_attribute("i", NetCDFArray::Byte(vec![1, -2]))
+        .add_attribute("s", NetCDFArray::Short(vec![3]))
+        .add_attribute("u", NetCDFArray::UInt64(vec![4]))
+        .add_dimension("time", Unlimited)
+        .add_dimension("x", Fixed(30))
+        .add_dimension("len", Fixed(4))
+        .add_variable("v", NetCDFType::NCFloat, &["x"])
+        .add_variable_attribute("v", "_FillValue", NetCDFArray::Float(vec![-1.0]))
+        .add_variable("names", NetCDFType::NCChar, &["time", "len"])
+        .add_variable("scalar", NetCDFType::NCDouble, &[])
+        .set_variable_data("v", NetCDFArray::Float((0..30).map(|i| if i == 2 { -1.0 } else { i as f32 * 1.5 }).collect()))
+        .set_variable_data("names", NetCDFArray::Char(b"ab\0\0cdef".to_vec()))
+        .build()
+        .unwrap();
+
+    assert_eq!(data.to_cdl("built").unwrap(), "\
+netcdf built {
+dimensions:
+\ttime = UNLIMITED ; // (2 currently)
+\tx = 30 ;
+\tlen = 4 ;
+variables:
+\tfloat v(x) ;
+\t\tv:_FillValue = -1.f ;
+\tchar names(time, len) ;
+\tdouble scalar ;
+
+// global attributes:
+\t\t:title = \"a \\\"quoted\\\"\\ttext\\n\",
+\t\t\t\"line2\" ;
+\t\t:f = 1.f, 0.1f, 1.e+20f, -3.5e-07f, NaNf ;
+\t\t:d = 1., 0.1, 1.23456789012346e+17 ;
+\t\t:i = 1b, -2b ;
+\t\t:s = 3s ;
+\t\t:u = 4UL ;
+data:
+
+ v = 0, 1.5, _, 4.5, 6, 7.5, 9, 10.5, 12, 13.5, 15, 16.5, 18, 19.5, 21, 22.5, 
+    24, 25.5, 27, 28.5, 30, 31.5, 33, 34.5, 36, 37.5, 39, 40.5, 42, 43.5 ;
+
+ names =
+  \"ab\",
+  \"cdef\" ;
+
+ scalar = _ ;
+}
+");
+}
+
+#[test]
+fn parse_cdl_file() {
+    let expected = load_file("tests/version1/record1.nc").unwrap();
+    let data = load_cdl_file("tests/cdl/record1.cdl", NetCDFVersion::CDF01).unwrap();
+
+    assert_eq!(data.num_of_records(), 3);
+    assert_eq!(data.to_cdl("record1").unwrap(), expected.to_cdl("record1").unwrap());
+
+    for variable in expected.list_of_variables() {
+        assert_eq!(data.variable_data(&variable.name).unwrap(), expected.variable_data(&variable.name).unwrap());
+    }
+}
+
+#[test]
+fn parse_cdl_round_trip() {
+    for name in ["small1", "small2", "record1", "record2"].iter() {
+        let data = load_file(format!("tests/version1/{}.nc", name)).unwrap();
+        let text = data.to_cdl(name).unwrap();
+        assert_eq!(parse_cdl(&text, NetCDFVersion::CDF01).unwrap().to_cdl(name).unwrap(), text);
+    }
+
+    let data = NetCDFBuilder::new(NetCDFVersion::CDF05)
+        .add_attribute("title", NetCDFArray::Char(b"tab\tquote\" newline\nend".to_vec()))
+        .add_attribute("f", NetCDFArray::Float(vec![1.0, 0.1, 1e20, f32::NAN, f32::NEG_INFINITY]))
+        .add_attribute("d", NetCDFArray::Double(vec![0.1, -2.5e-300]))
+        .add_attribute("b", NetCDFArray::Byte(vec![-128, 127]))
+        .add_attribute("u", NetCDFArray::UInt64(vec![u64::MAX]))
+        .add_attribute("l", NetCDFArray::Int64(vec![i64::MIN]))
+        .add_dimension("time", Unlimited)
+        .add_dimension("len", Fixed(3))
+        .add_variable("names", NetCDFType::NCChar, &["time", "len"])
+        .add_variable("us", NetCDFType::NCUShort, &["len"])
+        .add_variable_attribute("us", "valid_max", NetCDFArray::UShort(vec![1000]))
+        .set_variable_data("names", NetCDFArray::Char(b"ab\0xyz".to_vec()))
+        .set_variable_data("us", NetCDFArray::UShort(vec![1, 65535, 3]))
+        .build()
+        .unwrap();
+
+    let text = data.to_cdl("types").unwrap();
+    let parsed = parse_cdl(&text, NetCDFVersion::CDF05).unwrap();
+    assert_eq!(parsed.to_cdl("types").unwrap(), text);
+    // NaN is not equal to itself, so it is only checked in the text
+    assert_eq!(parsed.list_of_attributes()[2..], data.list_of_attributes()[2..]);
+    assert_eq!(parsed.variable_data("names").unwrap(), data.variable_data("names").unwrap());
+}
+
+#[test]
+fn parse_cdl_values() {
+    let text = r#"
+        netcdf values {
+        dimensions:
+            time = unlimited, x = 4 ;
+        variables:
+            int a(x), b(time, x) ;
+                a:_FillValue = -1 ;
+            double c ;
+            char s(x) ;
+            double :typed = 1 ;
+            :hex = 0x10s, 010s ;
+        data:
+            a = 1, _, 3 ;
+            b = 1, 2, 3, 4, 5 ;
+            c = 1e3 ;
+            s = "ab" ;
+        }
+    "#;
+
+    let data = parse_cdl(text, NetCDFVersion::CDF01).unwrap();
+    assert_eq!(data.num_of_records(), 2);
+    assert_eq!(data.variable_data("a").unwrap().values, NetCDFArray::Int(vec![1, -1, 3, -1]));
+    assert_eq!(data.variable_data("b").unwrap().values, NetCDFArray::Int(vec![1, 2, 3, 4, 5, -2147483647, -2147483647, -2147483647]));
+    assert_eq!(data.variable_data("c").unwrap().values, NetCDFArray::Double(vec![1000.0]));
+    assert_eq!(data.variable_data("s").unwrap().values, NetCDFArray::Char(b"ab\0\0".to_vec()));
+    assert_eq!(data.global_attribute("typed").unwrap().values, NetCDFArray::Double(vec![1.0]));
+    assert_eq!(data.global_attribute("hex").unwrap().values, NetCDFArray::Short(vec![16, 8]));
+}
+
+#[test]
+fn parse_cdl_errors() {
+    let parse = |text: &str| parse_cdl(text, NetCDFVersion::CDF01).err().map(|error| error.to_string());
+
+    assert_eq!(parse("netcdf x {\ndimensions:\n  x = 2 ;\nvariables:\n  int a(x) ;\ndata:\n  a = 1, 2, 3 ;\n}"),
+        Some("Invalid CDL: data of variable 'a': expected 2 values, got 3".to_string()));
+    assert_eq!(parse("netcdf x {\nvariables:\n  byte a ;\n  a:att = 1, 2\n}"),
+        Some("Invalid CDL: line 5: expected ';'".to_string()));
+    assert!(parse("netcdf x {\nvariables:\n  short a ;\ndata:\n  a = 40000 ;\n}").is_some());
+    assert!(parse("netcdf x {\ndata:\n  a = 1 ;\n}").is_some());
+    assert!(parse("netcdf x {\nvariables:\n  int a(y) ;\n}").is_some());
+    assert!(parse("netcdf x {\n  :title = \"unterminated ;\n}").is_some());
+    assert!(parse("netcdf x {\nvariables:\n  uint a ;\n}").is_some());
+    assert!(parse("netcdf x { } trailing").is_some());
+    assert_eq!(parse("netcdf x {\ndimensions:\n  x = 4294967296 ;\nvariables:\n  int a(x, x, x) ;\ndata:\n  a = 1 ;\n}"),
+        Some("Invalid CDL: data of variable 'a': the dimensions are too large".to_string()));
+}
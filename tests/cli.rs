@@ -0,0 +1,72 @@
+#![cfg(feature = "cli")]
+
+use std::process::Command;
+
+fn netcdfrs(args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_netcdfrs")).args(args).output().unwrap();
+    (output.status.success(), String::from_utf8(output.stdout).unwrap())
+}
+
+#[test]
+fn cli_dump_and_kind() {
+    assert_eq!(netcdfrs(&["kind", "tests/version1/record1.nc"]), (true, "classic\n".to_string()));
+    assert_eq!(netcdfrs(&["kind", "tests/version2/record1.nc"]), (true, "64-bit offset\n".to_string()));
+
+    let (success, output) = netcdfrs(&["dump", "tests/version1/record1.nc"]);
+    assert!(success);
+    assert_eq!(output, std::fs::read_to_string("tests/cdl/record1.cdl").unwrap()
+        .replace("// The same data set as tests/version1/record1.nc\n", ""));
+
+    let (success, output) = netcdfrs(&["dump", "-h", "tests/version1/record1.nc"]);
+    assert!(success);
+    assert!(output.ends_with("\tfloat temps(time, lat) ;\n}\n"));
+
+    let (success, output) = netcdfrs(&["dump", "-v", "times", "tests/version1/record1.nc"]);
+    assert!(success);
+    assert!(output.ends_with("data:\n\n times = 1, 2, 3 ;\n}\n"));
+
+    assert!(!netcdfrs(&["dump", "-v", "unknown", "tests/version1/record1.nc"]).0);
+    assert!(!netcdfrs(&["dump"]).0);
+    assert!(!netcdfrs(&["unknown", "tests/version1/record1.nc"]).0);
+}
+
+#[test]
+fn cli_json() {
+    let (success, output) = netcdfrs(&["json", "tests/version1/record1.nc"]);
+    assert!(success);
+    assert!(output.contains("\"kind\": \"classic\""));
+    assert!(output.contains("{\"name\": \"time\", \"length\": 3, \"unlimited\": true}"));
+    assert!(output.contains("{\"name\": \"temps\", \"type\": \"float\", \"dimensions\": [\"time\", \"lat\"], \"shape\": [3, 2]"));
+}
+
+#[test]
+fn cli_gen() {
+    let output = std::env::temp_dir().join("netcdfrs_cli_gen.nc");
+    let output_name = output.to_str().unwrap();
+
+    assert!(netcdfrs(&["gen", "-o", output_name, "tests/cdl/record1.cdl"]).0);
+    assert_eq!(std::fs::read(&output).unwrap(), std::fs::read("tests/version1/record1.nc").unwrap());
+
+    assert!(netcdfrs(&["gen", "-k", "64-bit offset", "-o", output_name, "tests/cdl/record1.cdl"]).0);
+    assert_eq!(netcdfrs(&["kind", output_name]), (true, "64-bit offset\n".to_string()));
+
+    assert!(!netcdfrs(&["gen", "-k", "netCDF-4", "-o", output_name, "tests/cdl/record1.cdl"]).0);
+    std::fs::remove_file(&output).unwrap();
+}
+
+#[test]
+fn cli_convert() {
+    let output = std::env::temp_dir().join("netcdfrs_cli_convert.nc");
+    let output_name = output.to_str().unwrap();
+
+    assert!(netcdfrs(&["convert", "-k", "cdf5", "tests/version1/record1.nc", output_name]).0);
+    assert_eq!(netcdfrs(&["kind", output_name]), (true, "cdf5\n".to_string()));
+
+    assert!(netcdfrs(&["convert", "-k", "classic", output_name, output_name]).0);
+    assert_eq!(std::fs::read(&output).unwrap(), std::fs::read("tests/version1/record1.nc").unwrap());
+
+    assert!(!netcdfrs(&["convert", "-k", "classic", "tests/version5/cdf5.nc", output_name]).0);
+    assert!(!netcdfrs(&["convert", "tests/version1/record1.nc", output_name]).0);
+    assert!(!netcdfrs(&["convert", "-k", "cdf5", "tests/version1/record1.nc"]).0);
+    std::fs::remove_file(&output).unwrap();
+}
@@ -0,0 +1,110 @@
+use std::io::Cursor;
+
+use netcdfrs::prelude::*;
+use netcdfrs::prelude::NetCDFDimensionLength::*;
+
+fn masked_file() -> Vec<u8> {
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(6))
+        .add_variable("temp", NetCDFType::NCFloat, &["x"])
+        .add_variable_attribute("temp", "_FillValue", NetCDFArray::Float(vec![-999.0]))
+        .add_variable_attribute("temp", "missing_value", NetCDFArray::Double(vec![-1.0, -2.0]))
+        .add_variable_attribute("temp", "valid_range", NetCDFArray::Float(vec![0.0, 100.0]))
+        .set_variable_data("temp", NetCDFArray::Float(vec![10.0, -999.0, -1.0, -2.0, 150.0, 100.0]))
+        .add_variable("count", NetCDFType::NCShort, &["x"])
+        .add_variable_attribute("count", "valid_min", NetCDFArray::Short(vec![1]))
+        .set_variable_data("count", NetCDFArray::Short(vec![0, 1, 2, -32767, 4, 5]))
+        .add_variable("flags", NetCDFType::NCByte, &["x"])
+        .set_variable_data("flags", NetCDFArray::Byte(vec![-127, 0, 1, 2, 3, 4]))
+        .build()
+        .unwrap();
+
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &netcdf).unwrap();
+    buffer
+}
+
+#[test]
+fn mask_fill_missing_and_range() {
+    let buffer = masked_file();
+    let data = load_reader(&mut &buffer[..]).unwrap();
+
+    let temp = data.variable_data_masked("temp").unwrap();
+    assert_eq!(temp.shape, vec![6]);
+    assert_eq!(temp.mask, vec![false, true, true, true, true, false]);
+    assert_eq!(temp.num_of_masked(), 4);
+    assert_eq!(temp.get(0), Some(NetCDFValue::Float(10.0)));
+    assert_eq!(temp.get(1), None);
+    assert_eq!(temp.to_options(NetCDFArray::as_f32_slice), Some(vec![Some(10.0), None, None, None, None, Some(100.0)]));
+    assert_eq!(temp.to_options(NetCDFArray::as_f64_slice), None);
+
+    // The default fill value is masked, too
+    let count = data.variable_data_masked("count").unwrap();
+    assert_eq!(count.to_options(NetCDFArray::as_i16_slice), Some(vec![None, Some(1), Some(2), None, Some(4), Some(5)]));
+
+    // But not for bytes without a _FillValue attribute
+    let flags = data.variable_data_masked("flags").unwrap();
+    assert_eq!(flags.num_of_masked(), 0);
+}
+
+#[test]
+fn mask_reader() {
+    let buffer = masked_file();
+    let mut reader = open_reader(Cursor::new(buffer)).unwrap();
+
+    let temp = reader.variable_data_masked("temp").unwrap();
+    assert_eq!(temp.mask, vec![false, true, true, true, true, false]);
+
+    let variable = reader.header().list_of_variables()[0].clone();
+    assert!(variable.is_valid(&NetCDFValue::Float(50.0)));
+    assert!(!variable.is_valid(&NetCDFValue::Float(-2.0)));
+    assert!(variable.is_fill_value(&NetCDFValue::Float(-999.0)));
+
+    let slice = reader.read_slice("temp", &[3], &[3], None).unwrap();
+    assert_eq!(variable.mask(&slice.values), vec![true, true, false]);
+}
+
+#[test]
+fn unpack_and_pack() {
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(4))
+        .add_variable("temp", NetCDFType::NCShort, &["x"])
+        .add_variable_attribute("temp", "_FillValue", NetCDFArray::Short(vec![-1]))
+        .set_variable_data_packed("temp", NetCDFArray::Double(vec![273.15, 280.0, f64::NAN, 300.5]), 0.01, 273.15)
+        .add_variable("level", NetCDFType::NCByte, &["x"])
+        .set_variable_data_packed("level", NetCDFArray::Float(vec![0.0, 0.5, 1.0, 1.5]), 0.5, 0.0)
+        .add_variable("raw", NetCDFType::NCInt, &["x"])
+        .set_variable_data("raw", NetCDFArray::Int(vec![1, 2, 3, 4]))
+        .build()
+        .unwrap();
+
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &netcdf).unwrap();
+    let data = load_reader(&mut &buffer[..]).unwrap();
+
+    assert_eq!(data.variable_data("temp").unwrap().values, NetCDFArray::Short(vec![0, 685, -1, 2735]));
+
+    // Double attributes give double values
+    let temp = data.variable_data_unpacked("temp").unwrap();
+    assert_eq!(temp.values.nc_type(), NetCDFType::NCDouble);
+    let values = temp.to_f64_options();
+    assert!((values[0].unwrap() - 273.15).abs() < 1e-9);
+    assert!((values[1].unwrap() - 280.0).abs() < 1e-9);
+    assert_eq!(values[2], None);
+    assert!((values[3].unwrap() - 300.5).abs() < 1e-9);
+
+    // Float attributes give float values
+    let level = data.variable_data_unpacked("level").unwrap();
+    assert_eq!(level.to_options(NetCDFArray::as_f32_slice), Some(vec![Some(0.0), Some(0.5), Some(1.0), Some(1.5)]));
+
+    // Not packed at all
+    let raw = data.variable_data_unpacked("raw").unwrap();
+    assert_eq!(raw.values, NetCDFArray::Double(vec![1.0, 2.0, 3.0, 4.0]));
+
+    let result = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", Fixed(1))
+        .add_variable("level", NetCDFType::NCByte, &["x"])
+        .set_variable_data_packed("level", NetCDFArray::Float(vec![1000.0]), 1.0, 0.0)
+        .build();
+    assert!(result.is_err());
+}
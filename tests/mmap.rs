@@ -0,0 +1,69 @@
+#![cfg(feature = "mmap")]
+
+use netcdfrs::prelude::*;
+
+#[test]
+fn mmap_record1() {
+    let data = open_file_mmap("tests/version1/record1.nc").unwrap();
+
+    assert_eq!(data.header().num_of_records(), 3);
+
+    let temps = data.variable_data("temps").unwrap();
+    assert_eq!(temps.shape, vec![3, 2]);
+    assert_eq!(temps.values.as_f32_slice(), Some(&[280.0, 281.5, 282.0, 283.5, 284.0, 285.5][..]));
+
+    let temps = data.read_slice("temps", &[0, 1], &[2, 1], Some(&[2, 1])).unwrap();
+    assert_eq!(temps.values.as_f32_slice(), Some(&[281.5, 285.5][..]));
+
+    let times = data.variable_data("times").unwrap();
+    assert_eq!(times.values.as_i16_slice(), Some(&[1, 2, 3][..]));
+}
+
+#[test]
+fn mmap_small2() {
+    let data = open_file_mmap("tests/version1/small2.nc").unwrap();
+
+    let temps = data.variable_data("temps").unwrap();
+    assert_eq!(temps.values.as_i16_slice(), Some(&[30, 32, 34, 36, 40][..]));
+
+    let temps = data.variable_data_masked("temps").unwrap();
+    assert_eq!(temps.mask, vec![false; 5]);
+
+    let temps = data.variable_data_unpacked("temps").unwrap();
+    assert_eq!(temps.values, NetCDFArray::Double(vec![30.0, 32.0, 34.0, 36.0, 40.0]));
+}
+
+#[test]
+fn mmap_record1_streaming() {
+    let data = open_file_mmap("tests/version1/record1_streaming.nc").unwrap();
+
+    assert_eq!(data.header().num_of_records(), 3);
+
+    let times = data.variable_data("times").unwrap();
+    assert_eq!(times.values.as_i16_slice(), Some(&[1, 2, 3][..]));
+}
+
+#[test]
+fn mmap_invalid_offset() {
+    let data = NetCDFBuilder::new(NetCDFVersion::CDF02)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(3))
+        .add_variable("v", NetCDFType::NCInt, &["x"])
+        .build()
+        .unwrap();
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &data).unwrap();
+
+    // The offset of the only variable is the end of the header
+    let position = (0..buffer.len() - 8)
+        .find(|i| buffer[*i..*i + 8] == ((*i + 8) as u64).to_be_bytes())
+        .unwrap();
+    buffer[position..position + 8].copy_from_slice(&(u64::MAX - 4).to_be_bytes());
+
+    let path = std::env::temp_dir().join("netcdfrs_mmap_invalid_offset.nc");
+    std::fs::write(&path, &buffer).unwrap();
+    let mmap = open_file_mmap(&path).unwrap();
+    assert!(mmap.variable_data("v").is_err());
+    assert!(mmap.read_slice("v", &[2], &[1], None).is_err());
+    drop(mmap);
+    std::fs::remove_file(&path).unwrap();
+}
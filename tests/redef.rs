@@ -0,0 +1,195 @@
+use std::fs;
+use std::io::Cursor;
+
+use netcdfrs::prelude::*;
+use netcdfrs::prelude::NetCDFDimensionLength::*;
+
+#[test]
+fn redef_record1() {
+    let mut buffer = fs::read("tests/version1/record1.nc").unwrap();
+    let header = open_redefiner(Cursor::new(&mut buffer)).unwrap()
+        .add_dimension("lon", Fixed(3))
+        .add_attribute("history", NetCDFArray::Char(b"redef".to_vec()))
+        .add_variable("elevation", NetCDFType::NCShort, &["lat", "lon"])
+        .add_variable("pressure", NetCDFType::NCDouble, &["time"])
+        .add_variable_attribute("temps", "units", NetCDFArray::Char(b"K".to_vec()))
+        .add_variable_attribute("pressure", "units", NetCDFArray::Char(b"hPa".to_vec()))
+        .enddef()
+        .unwrap();
+
+    assert_eq!(header.num_of_variables(), 5);
+    assert_eq!(header.num_of_records(), 3);
+
+    let data = load_reader(&mut &buffer[..]).unwrap();
+    let old = load_file("tests/version1/record1.nc").unwrap();
+
+    for name in ["lat", "times", "temps"].iter() {
+        assert_eq!(data.variable_data(name).unwrap(), old.variable_data(name).unwrap());
+    }
+
+    assert_eq!(data.variable_data("elevation").unwrap().values, NetCDFArray::Short(vec![-32767; 6]));
+    assert_eq!(data.variable_data("pressure").unwrap().values.as_f64_slice(), Some(&[9.969_209_968_386_869e36; 3][..]));
+
+    // Same bytes as a file that is created from scratch
+    let expected = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", Unlimited)
+        .add_dimension("lat", Fixed(2))
+        .add_dimension("lon", Fixed(3))
+        .add_attribute("history", NetCDFArray::Char(b"redef".to_vec()))
+        .add_variable("lat", NetCDFType::NCFloat, &["lat"])
+        .add_variable("times", NetCDFType::NCShort, &["time"])
+        .add_variable("temps", NetCDFType::NCFloat, &["time", "lat"])
+        .add_variable_attribute("temps", "units", NetCDFArray::Char(b"K".to_vec()))
+        .add_variable("elevation", NetCDFType::NCShort, &["lat", "lon"])
+        .add_variable("pressure", NetCDFType::NCDouble, &["time"])
+        .add_variable_attribute("pressure", "units", NetCDFArray::Char(b"hPa".to_vec()))
+        .set_variable_data("lat", old.variable_data("lat").unwrap().values)
+        .set_variable_data("times", old.variable_data("times").unwrap().values)
+        .set_variable_data("temps", old.variable_data("temps").unwrap().values)
+        .build()
+        .unwrap();
+    let mut expected_buffer = Vec::new();
+    save_writer(&mut expected_buffer, &expected).unwrap();
+
+    assert_eq!(buffer, expected_buffer);
+}
+
+#[test]
+fn redef_single_record_variable() {
+    let mut buffer = fs::read("tests/version1/record2.nc").unwrap();
+    open_redefiner(Cursor::new(&mut buffer)).unwrap()
+        .add_variable("flags", NetCDFType::NCByte, &["time"])
+        .enddef()
+        .unwrap();
+
+    let expected = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", Unlimited)
+        .add_variable("times", NetCDFType::NCShort, &["time"])
+        .add_variable("flags", NetCDFType::NCByte, &["time"])
+        .set_variable_data("times", NetCDFArray::Short(vec![1, 2, 3]))
+        .build()
+        .unwrap();
+    let mut expected_buffer = Vec::new();
+    save_writer(&mut expected_buffer, &expected).unwrap();
+
+    assert_eq!(buffer, expected_buffer);
+}
+
+#[test]
+fn redef_file() {
+    let path = std::env::temp_dir().join("netcdfrs_redef_file.nc");
+    fs::copy("tests/version1/small2.nc", &path).unwrap();
+
+    open_redef(&path).unwrap()
+        .add_attribute("title", NetCDFArray::Char(b"small".to_vec()))
+        .enddef()
+        .unwrap();
+
+    let data = load_file(&path).unwrap();
+    assert_eq!(data.list_of_attributes().last().unwrap().values.as_string(), Some("small".to_string()));
+    assert_eq!(data.variable_data("temps").unwrap().values.as_i16_slice(), Some(&[30, 32, 34, 36, 40][..]));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn redef_errors() {
+    let buffer = fs::read("tests/version1/record1.nc").unwrap();
+
+    let result = open_redefiner(Cursor::new(buffer.clone())).unwrap()
+        .add_variable("temps", NetCDFType::NCFloat, &["lat"])
+        .enddef();
+    assert!(result.is_err());
+
+    let result = open_redefiner(Cursor::new(buffer.clone())).unwrap()
+        .add_dimension("depth", Unlimited)
+        .enddef();
+    assert!(result.is_err());
+
+    let result = open_redefiner(Cursor::new(buffer.clone())).unwrap()
+        .add_variable("depth", NetCDFType::NCFloat, &["lat", "time"])
+        .enddef();
+    assert!(result.is_err());
+
+    let result = open_redefiner(Cursor::new(buffer)).unwrap()
+        .add_variable_attribute("depth", "units", NetCDFArray::Char(b"m".to_vec()))
+        .enddef();
+    assert!(result.is_err());
+}
+
+#[test]
+fn redef_in_place() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+    let options = NetCDFWriteOptions{h_minfree: 128, v_minfree: 64, ..NetCDFWriteOptions::default()};
+    let mut buffer = Vec::new();
+    save_writer_with_options(&mut buffer, &data, &options).unwrap();
+    let old_buffer = buffer.clone();
+
+    let header = open_redefiner(Cursor::new(&mut buffer)).unwrap()
+        .add_attribute("history", NetCDFArray::Char(b"attribute added in place".to_vec()))
+        .enddef()
+        .unwrap();
+    assert_eq!(header.num_of_attributes(), 1);
+
+    // Only the header has changed
+    let data_begin = buffer.len() - ((3 * 12) + 8 + 64);
+    assert_eq!(buffer.len(), old_buffer.len());
+    assert_eq!(&buffer[data_begin..], &old_buffer[data_begin..]);
+
+    let result = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(result.variable_data("temps").unwrap(), data.variable_data("temps").unwrap());
+
+    // A new fixed size variable fits into the free space in front of the records
+    let records = buffer[buffer.len() - (3 * 12)..].to_vec();
+    open_redefiner(Cursor::new(&mut buffer)).unwrap()
+        .add_variable("flags", NetCDFType::NCShort, &["lat"])
+        .enddef()
+        .unwrap();
+    assert_eq!(buffer.len(), old_buffer.len());
+    assert_eq!(&buffer[buffer.len() - (3 * 12)..], &records[..]);
+
+    let result = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(result.variable_data("flags").unwrap().values, NetCDFArray::Short(vec![-32767, -32767]));
+    assert_eq!(result.variable_data("lat").unwrap(), data.variable_data("lat").unwrap());
+}
+
+#[test]
+fn redef_fill_mode() {
+    let mut buffer = fs::read("tests/version1/record1.nc").unwrap();
+    open_redefiner(Cursor::new(&mut buffer)).unwrap()
+        .add_variable("pressure", NetCDFType::NCInt, &["time"])
+        .add_variable_attribute("pressure", "_FillValue", NetCDFArray::Int(vec![0]))
+        .enddef()
+        .unwrap();
+
+    let data = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(data.variable_data("pressure").unwrap().values, NetCDFArray::Int(vec![0, 0, 0]));
+
+    // Without filling the file is only extended
+    let mut buffer = fs::read("tests/version1/small2.nc").unwrap();
+    let old_len = buffer.len();
+    open_redefiner(Cursor::new(&mut buffer)).unwrap()
+        .add_variable("flags", NetCDFType::NCInt, &["temp"])
+        .set_fill_mode(NetCDFFillMode::NoFill)
+        .enddef()
+        .unwrap();
+
+    let data = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(data.variable_data("flags").unwrap().values, NetCDFArray::Int(vec![0; 5]));
+    assert!(buffer.len() > old_len);
+}
+
+#[test]
+fn redef_cdf5() {
+    let mut buffer = fs::read("tests/version5/cdf5.nc").unwrap();
+    let header = open_redefiner(Cursor::new(&mut buffer)).unwrap()
+        .add_variable_attribute("u64", "long_name", NetCDFArray::Char(b"unsigned 64 bit".to_vec()))
+        .enddef()
+        .unwrap();
+
+    assert_eq!(header.version(), NetCDFVersion::CDF05);
+
+    let data = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(data.variable_data("u64").unwrap().values, NetCDFArray::UInt64(vec![(1 << 63) + 5, 0, 1]));
+    assert_eq!(data.variable_data("rec").unwrap().values, NetCDFArray::Int64(vec![10, 20]));
+}
@@ -4,17 +4,230 @@ use netcdfrs::prelude::*;
 fn empty() {
     let data = load_file("tests/version1/empty.nc").unwrap();
 
-
+    assert_eq!(data.num_of_records(), 0);
+    assert_eq!(data.num_of_dimensions(), 0);
+    assert_eq!(data.num_of_attributes(), 0);
+    assert_eq!(data.num_of_variables(), 0);
 }
 
 #[test]
 fn small1() {
     let data = load_file("tests/version1/small1.nc").unwrap();
 
+    assert_eq!(data.num_of_dimensions(), 1);
+    assert_eq!(data.num_of_variables(), 1);
+    assert_eq!(data.list_of_variables()[0].name, "times");
 }
 
 #[test]
 fn small2() {
     let data = load_file("tests/version1/small2.nc").unwrap();
 
+    assert_eq!(data.num_of_dimensions(), 2);
+    assert_eq!(data.num_of_variables(), 2);
+    assert_eq!(data.list_of_variables()[1].name, "temps");
+}
+
+#[test]
+fn record1() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+
+    assert_eq!(data.num_of_records(), 3);
+    assert_eq!(data.num_of_dimensions(), 2);
+    assert_eq!(data.num_of_variables(), 3);
+}
+
+#[test]
+fn record2() {
+    let data = load_file("tests/version1/record2.nc").unwrap();
+
+    assert_eq!(data.num_of_records(), 3);
+    assert_eq!(data.num_of_variables(), 1);
+}
+
+#[test]
+fn variable_data() {
+    let data = load_file("tests/version1/small2.nc").unwrap();
+
+    let temps = data.variable_data("temps").unwrap();
+    assert_eq!(temps.shape, vec![5]);
+    assert_eq!(temps.values.as_i16_slice(), Some(&[30, 32, 34, 36, 40][..]));
+    assert_eq!(temps.values.get(4), Some(NetCDFValue::Short(40)));
+    assert_eq!(temps.values.as_f64_slice(), None);
+
+    assert!(data.variable_data("unknown").is_err());
+}
+
+#[test]
+fn variable_data_record() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+
+    let lat = data.variable_data("lat").unwrap();
+    assert_eq!(lat.shape, vec![2]);
+    assert_eq!(lat.values, NetCDFArray::Float(vec![10.5, 20.5]));
+
+    let times = data.variable_data("times").unwrap();
+    assert_eq!(times.shape, vec![3]);
+    assert_eq!(times.values, NetCDFArray::Short(vec![1, 2, 3]));
+
+    let temps = data.variable_data("temps").unwrap();
+    assert_eq!(temps.shape, vec![3, 2]);
+    assert_eq!(temps.values, NetCDFArray::Float(vec![280.0, 281.5, 282.0, 283.5, 284.0, 285.5]));
+}
+
+#[test]
+fn open_record1() {
+    let mut reader = open_file("tests/version1/record1.nc").unwrap();
+
+    assert_eq!(reader.header().num_of_records(), 3);
+    assert_eq!(reader.header().num_of_variables(), 3);
+
+    let temps = reader.variable_data("temps").unwrap();
+    assert_eq!(temps.shape, vec![3, 2]);
+    assert_eq!(temps.values.as_f32_slice(), Some(&[280.0, 281.5, 282.0, 283.5, 284.0, 285.5][..]));
+
+    let lat = reader.variable_data("lat").unwrap();
+    assert_eq!(lat.values.as_f32_slice(), Some(&[10.5, 20.5][..]));
+}
+
+#[test]
+fn open_record2() {
+    let mut reader = open_file("tests/version1/record2.nc").unwrap();
+
+    let times = reader.variable_data("times").unwrap();
+    assert_eq!(times.shape, vec![3]);
+    assert_eq!(times.values.as_i16_slice(), Some(&[1, 2, 3][..]));
+}
+
+#[test]
+fn read_slice() {
+    let mut reader = open_file("tests/version1/small2.nc").unwrap();
+
+    let temps = reader.read_slice("temps", &[1], &[2], Some(&[2])).unwrap();
+    assert_eq!(temps.shape, vec![2]);
+    assert_eq!(temps.values.as_i16_slice(), Some(&[32, 36][..]));
+
+    assert!(reader.read_slice("temps", &[1], &[3], Some(&[2])).is_err());
+    assert!(reader.read_slice("temps", &[0, 0], &[1, 1], None).is_err());
+
+    // Must not overflow
+    assert!(reader.read_slice("temps", &[1], &[usize::MAX], None).is_err());
+    assert!(reader.read_slice("temps", &[0], &[2], Some(&[usize::MAX])).is_err());
+    assert!(reader.read_slice("temps", &[usize::MAX], &[2], None).is_err());
+}
+
+#[test]
+fn read_slice_record() {
+    let mut reader = open_file("tests/version1/record1.nc").unwrap();
+
+    let temps = reader.read_slice("temps", &[1, 0], &[2, 2], None).unwrap();
+    assert_eq!(temps.shape, vec![2, 2]);
+    assert_eq!(temps.values.as_f32_slice(), Some(&[282.0, 283.5, 284.0, 285.5][..]));
+
+    let temps = reader.read_slice("temps", &[0, 1], &[2, 1], Some(&[2, 1])).unwrap();
+    assert_eq!(temps.values.as_f32_slice(), Some(&[281.5, 285.5][..]));
+
+    let times = reader.read_slice("times", &[1], &[2], None).unwrap();
+    assert_eq!(times.values.as_i16_slice(), Some(&[2, 3][..]));
+}
+
+#[test]
+fn record1_streaming() {
+    let data = load_file("tests/version1/record1_streaming.nc").unwrap();
+
+    assert_eq!(data.num_of_records(), 3);
+    assert_eq!(data.variable_data("times").unwrap().values.as_i16_slice(), Some(&[1, 2, 3][..]));
+
+    let mut reader = open_file("tests/version1/record1_streaming.nc").unwrap();
+
+    assert_eq!(reader.header().num_of_records(), 3);
+    let temps = reader.variable_data("temps").unwrap();
+    assert_eq!(temps.shape, vec![3, 2]);
+    assert_eq!(temps.values.as_f32_slice(), Some(&[280.0, 281.5, 282.0, 283.5, 284.0, 285.5][..]));
+}
+
+#[test]
+fn lookup_by_name() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+
+    let time = data.unlimited_dimension().unwrap();
+    assert_eq!(data.dimension(&time.name).unwrap().length, 0);
+    assert!(data.dimension("unknown").is_none());
+    assert!(data.variable("unknown").is_none());
+    assert!(data.global_attribute("unknown").is_none());
+
+    for variable in data.list_of_variables() {
+        let found = data.variable(&variable.name).unwrap();
+        let dimensions = data.variable_dimensions(found);
+        assert_eq!(dimensions.len(), variable.dimid.len());
+        assert_eq!(data.is_record_variable(found), dimensions[0].name == time.name);
+
+        let shape = data.shape(found);
+        assert_eq!(shape, data.variable_data(&variable.name).unwrap().shape);
+        if data.is_record_variable(found) {
+            assert_eq!(shape[0], 3);
+        }
+    }
+
+    let small = load_file("tests/version1/small2.nc").unwrap();
+    assert!(small.unlimited_dimension().is_none());
+    assert!(!small.is_record_variable(small.variable("temps").unwrap()));
+
+    let built = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_attribute("title", NetCDFArray::Char(b"lookup".to_vec()))
+        .add_dimension("x", NetCDFDimensionLength::Fixed(2))
+        .add_variable("v", NetCDFType::NCInt, &["x"])
+        .add_variable_attribute("v", "units", NetCDFArray::Char(b"m".to_vec()))
+        .build()
+        .unwrap();
+    assert_eq!(built.global_attribute("title").unwrap().values.as_string(), Some("lookup".to_string()));
+    assert_eq!(built.variable("v").unwrap().attribute("units").unwrap().values.as_string(), Some("m".to_string()));
+    assert!(built.variable("v").unwrap().attribute("long_name").is_none());
+}
+
+#[test]
+fn large_numrecs() {
+    // Unlimited dimension "t" without any variables
+    let mut buffer = b"CDF\x01\x0f\xff\xff\xff".to_vec();
+    buffer.extend_from_slice(&[0, 0, 0, 0x0a, 0, 0, 0, 1, 0, 0, 0, 1, b't', 0, 0, 0, 0, 0, 0, 0]);
+    buffer.extend_from_slice(&[0; 16]);
+    let data = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(data.num_of_dimensions(), 1);
+    assert_eq!(data.num_of_variables(), 0);
+
+    // More records than the file can hold
+    let mut buffer = std::fs::read("tests/version1/record1.nc").unwrap();
+    buffer[4..8].copy_from_slice(&0x0fff_ffffu32.to_be_bytes());
+    assert!(matches!(open_reader(std::io::Cursor::new(&buffer)), Err(NetCDFError::InvalidData(_))));
+    assert!(load_reader(&mut &buffer[..]).is_err());
+
+    let path = std::env::temp_dir().join("netcdfrs_large_numrecs.nc");
+    std::fs::write(&path, &buffer).unwrap();
+    assert!(matches!(load_file(&path), Err(NetCDFError::InvalidData(_))));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn read_slice_large_dimensions() {
+    let data = NetCDFBuilder::new(NetCDFVersion::CDF02)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(11))
+        .add_dimension("y", NetCDFDimensionLength::Fixed(13))
+        .add_dimension("z", NetCDFDimensionLength::Fixed(17))
+        .add_variable("v", NetCDFType::NCInt, &["x", "y", "z"])
+        .build()
+        .unwrap();
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &data).unwrap();
+
+    // All three dimensions get the length 0xFFFFFFF0 in the header
+    for (name, length) in [(b'x', 11u32), (b'y', 13), (b'z', 17)] {
+        let mut entry = vec![name, 0, 0, 0];
+        entry.extend_from_slice(&length.to_be_bytes());
+        let position = buffer.windows(8).position(|bytes| bytes == &entry[..]).unwrap() + 4;
+        buffer[position..position + 4].copy_from_slice(&0xffff_fff0u32.to_be_bytes());
+    }
+
+    let mut reader = open_reader(std::io::Cursor::new(&buffer)).unwrap();
+    assert!(matches!(reader.read_slice("v", &[1, 1, 1], &[1, 1, 1], None), Err(NetCDFError::InvalidData(_))));
+    assert!(matches!(reader.read_slice("v", &[0, 0, 0], &[0xffff_fff0; 3], None), Err(NetCDFError::InvalidSlice(_))));
 }
@@ -0,0 +1,98 @@
+use netcdfrs::prelude::*;
+use netcdfrs::prelude::NetCDFDimensionLength::*;
+
+fn date(year: i64, month: u32, day: u32) -> NetCDFDateTime {
+    NetCDFDateTime::new(year, month, day, 0, 0, 0)
+}
+
+fn decode(units: &str, calendar: &str, value: f64) -> NetCDFDateTime {
+    let units = NetCDFTimeUnits::parse(units, NetCDFCalendar::from_name(calendar).unwrap()).unwrap();
+    let datetime = units.decode(value).unwrap();
+    assert_eq!(units.encode(&datetime).unwrap(), value);
+    datetime
+}
+
+#[test]
+fn calendars() {
+    assert_eq!(decode("days since 1970-01-01", "proleptic_gregorian", 18262.0), date(2020, 1, 1));
+    assert_eq!(decode("days since 1970-01-01", "gregorian", -1.0), date(1969, 12, 31));
+
+    // The standard calendar switches from Julian to Gregorian
+    assert_eq!(decode("days since 1582-10-01", "standard", 3.0), date(1582, 10, 4));
+    assert_eq!(decode("days since 1582-10-01", "standard", 4.0), date(1582, 10, 15));
+    assert_eq!(decode("days since 1582-10-01", "proleptic_gregorian", 4.0), date(1582, 10, 5));
+    assert_eq!(decode("days since 1500-03-01", "standard", -1.0), date(1500, 2, 29));
+
+    assert_eq!(decode("days since 1900-01-01", "julian", 36525.0), date(2000, 1, 1));
+    assert_eq!(decode("days since 2000-01-01", "noleap", 59.0), date(2000, 3, 1));
+    assert_eq!(decode("days since 2000-01-01", "365_day", 365.0), date(2001, 1, 1));
+    assert_eq!(decode("days since 2000-01-01", "all_leap", 59.0), date(2000, 2, 29));
+    assert_eq!(decode("days since 2001-01-01", "366_day", 366.0), date(2002, 1, 1));
+    assert_eq!(decode("days since 2000-01-01", "360_day", 30.0), date(2000, 2, 1));
+    assert_eq!(decode("days since 2000-01-01", "360_day", 359.0), date(2000, 12, 30));
+    assert_eq!(decode("days since 2000-01-01", "360_day", -1.0), date(1999, 12, 30));
+
+    assert!(NetCDFCalendar::from_name("lunar").is_err());
+
+    let units = NetCDFTimeUnits::parse("days since 1582-10-01", NetCDFCalendar::Standard).unwrap();
+    assert!(units.encode(&date(1582, 10, 10)).is_err());
+
+    let units = NetCDFTimeUnits::parse("days since 2000-01-01", NetCDFCalendar::Day360).unwrap();
+    assert!(units.encode(&date(2000, 1, 31)).is_err());
+}
+
+#[test]
+fn units() {
+    assert_eq!(decode("hours since 2000-01-01 00:00:00 +01:00", "standard", 0.0),
+        NetCDFDateTime::new(1999, 12, 31, 23, 0, 0));
+    assert_eq!(decode("hours since 2000-01-01 00:00:00", "standard", 36.5),
+        NetCDFDateTime::new(2000, 1, 2, 12, 30, 0));
+    assert_eq!(decode("seconds since 1970-01-01T00:00:00Z", "standard", 1.5),
+        NetCDFDateTime{microsecond: 500_000, ..NetCDFDateTime::new(1970, 1, 1, 0, 0, 1)});
+    assert_eq!(decode("minutes since 1-1-1 0:0:0", "julian", 1440.0), date(1, 1, 2));
+    assert_eq!(decode("Days Since 1850-01-01 00:00:00.0", "standard", 0.0), date(1850, 1, 1));
+
+    let units = NetCDFTimeUnits::parse("days since 1850-01-01", NetCDFCalendar::NoLeap).unwrap();
+    assert_eq!(units.unit, NetCDFTimeUnit::Days);
+    assert_eq!(units.to_string(), "days since 1850-01-01 00:00:00");
+
+    assert!(NetCDFTimeUnits::parse("days after 1850-01-01", NetCDFCalendar::Standard).is_err());
+    assert!(NetCDFTimeUnits::parse("fortnights since 1850-01-01", NetCDFCalendar::Standard).is_err());
+    assert!(NetCDFTimeUnits::parse("days since 1850-13-01", NetCDFCalendar::Standard).is_err());
+
+    // Out of range instead of overflowing
+    for calendar in ["standard", "proleptic_gregorian", "julian", "noleap", "all_leap", "360_day"] {
+        let calendar = NetCDFCalendar::from_name(calendar).unwrap();
+        assert!(NetCDFTimeUnits::parse("days since 300000-01-01", calendar).is_err());
+        assert!(NetCDFTimeUnits::parse("days since -300000-01-01", calendar).is_err());
+        assert!(NetCDFTimeUnits::parse("days since 9223372036854775807-01-01", calendar).is_err());
+        assert!(NetCDFTimeUnits::parse("days since -9223372036854775807-01-01", calendar).is_err());
+        assert!(NetCDFTimeUnits::parse("days since 2000-01-01 00:00 +99999999999999999:00", calendar).is_err());
+    }
+
+    let units = NetCDFTimeUnits::parse("days since 280000-01-01", NetCDFCalendar::Standard).unwrap();
+    assert_eq!(units.decode(1.0).unwrap(), date(280000, 1, 2));
+    assert!(units.decode(3e6).is_err());
+    assert!(units.decode(1e300).is_err());
+    assert!(units.encode(&date(-280000, 1, 1)).is_err());
+    assert!(units.encode(&date(300000, 1, 1)).is_err());
+}
+
+#[test]
+fn time_variable() {
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", Unlimited)
+        .add_variable("time", NetCDFType::NCDouble, &["time"])
+        .add_variable_attribute("time", "units", NetCDFArray::Char(b"days since 2000-01-01".to_vec()))
+        .add_variable_attribute("time", "calendar", NetCDFArray::Char(b"noleap".to_vec()))
+        .set_variable_data("time", NetCDFArray::Double(vec![0.0, 59.0, 9.969209968386869e36]))
+        .build()
+        .unwrap();
+
+    let variable = &netcdf.list_of_variables()[0];
+    let units = NetCDFTimeUnits::from_variable(variable).unwrap();
+    assert_eq!(units.calendar, NetCDFCalendar::NoLeap);
+
+    let times = units.decode_data(&netcdf.variable_data_masked("time").unwrap()).unwrap();
+    assert_eq!(times, vec![Some(date(2000, 1, 1)), Some(date(2000, 3, 1)), None]);
+}
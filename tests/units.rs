@@ -0,0 +1,83 @@
+use netcdfrs::prelude::*;
+
+fn unit(text: &str) -> NetCDFUnit {
+    NetCDFUnit::parse(text).unwrap()
+}
+
+fn convert(value: f64, from: &str, to: &str) -> f64 {
+    unit(from).convert(value, &unit(to)).unwrap()
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() <= 1e-9 * b.abs().max(1.0), "{} != {}", a, b);
+}
+
+#[test]
+fn parse_units() {
+    assert!(unit("m s-1").is_compatible(&unit("m/s")));
+    assert!(unit("m s-1").is_compatible(&unit("km.h-1")));
+    assert!(unit("m s-1").is_compatible(&unit("knots")));
+    assert!(unit("kg m-2 s-1").is_compatible(&unit("kg/m^2/s")));
+    assert!(unit("kg m-2 s-1").is_compatible(&unit("mm/day kg m-3")));
+    assert!(unit("W m-2").is_compatible(&unit("J m**-2 s-1")));
+    assert!(unit("Pa").is_compatible(&unit("N m-2")));
+    assert!(unit("degrees_north").is_dimensionless());
+    assert!(unit("1").is_dimensionless());
+
+    assert!(!unit("m s-1").is_compatible(&unit("m s-2")));
+    assert!(!unit("K").is_compatible(&unit("Pa")));
+    assert!(NetCDFUnit::parse("furlongs").is_err());
+    assert!(NetCDFUnit::parse("").is_err());
+
+    // Exponents out of range are errors, not overflows
+    assert!(NetCDFUnit::parse("kg127 kg127").is_err());
+    assert!(NetCDFUnit::parse("1/kg-128").is_err());
+    assert!(NetCDFUnit::parse("L^50").is_err());
+    assert!(unit("kg127 kg-127").is_dimensionless());
+}
+
+#[test]
+fn convert_units() {
+    assert_close(convert(273.15, "K", "degC"), 0.0);
+    assert_close(convert(-40.0, "degC", "degF"), -40.0);
+    assert_close(convert(212.0, "degF", "K"), 373.15);
+    // A temperature difference has no offset
+    assert_close(convert(1.0, "K s-1", "degC s-1"), 1.0);
+
+    assert_close(convert(101325.0, "Pa", "hPa"), 1013.25);
+    assert_close(convert(1.0, "bar", "hPa"), 1000.0);
+    assert_close(convert(36.0, "km/h", "m s-1"), 10.0);
+    assert_close(convert(1.0, "ms", "s"), 0.001);
+    assert_close(convert(2.0, "days", "hours"), 48.0);
+    assert_close(convert(1.0, "kg m-2 s-1", "mm/day 1000 kg m-3"), 86400.0);
+    assert_close(convert(50.0, "percent", "1"), 0.5);
+    assert_close(convert(3.0, "0.5 m", "m"), 1.5);
+    assert_close(convert(1.5, "1.5e3 m", "km"), 2.25);
+
+    assert!(unit("K").convert(1.0, &unit("m")).is_err());
+}
+
+#[test]
+fn convert_variable_data() {
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(3))
+        .add_variable("temperature", NetCDFType::NCFloat, &["x"])
+        .add_variable_attribute("temperature", "units", NetCDFArray::Char(b"K".to_vec()))
+        .add_variable_attribute("temperature", "_FillValue", NetCDFArray::Float(vec![-1.0]))
+        .set_variable_data("temperature", NetCDFArray::Float(vec![273.15, -1.0, 300.0]))
+        .build()
+        .unwrap();
+
+    let variable = &netcdf.list_of_variables()[0];
+    let kelvin = NetCDFUnit::from_variable(variable).unwrap();
+    let data = netcdf.variable_data_masked("temperature").unwrap();
+    let celsius = kelvin.convert_data(&data, &unit("degC")).unwrap();
+
+    assert_eq!(celsius.mask, vec![false, true, false]);
+    if let NetCDFArray::Float(values) = celsius.values {
+        assert!(values[0].abs() < 1e-4);
+        assert!((values[2] - 26.85).abs() < 1e-4);
+    } else {
+        panic!("float values expected");
+    }
+}
@@ -0,0 +1,299 @@
+use std::fs;
+use std::convert::TryInto;
+use std::io::{Seek, SeekFrom, Write};
+
+use netcdfrs::prelude::*;
+
+fn round_trip(path: &str) {
+    let data = load_file(path).unwrap();
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, &data).unwrap();
+
+    assert_eq!(buffer, fs::read(path).unwrap());
+}
+
+#[test]
+fn round_trip_empty() {
+    round_trip("tests/version1/empty.nc");
+}
+
+#[test]
+fn round_trip_small() {
+    round_trip("tests/version1/small1.nc");
+    round_trip("tests/version1/small2.nc");
+}
+
+#[test]
+fn round_trip_record() {
+    round_trip("tests/version1/record1.nc");
+    round_trip("tests/version1/record2.nc");
+}
+
+#[test]
+fn save_file_record() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+    let path = std::env::temp_dir().join("netcdfrs_save_file_record.nc");
+    save_file(&path, &data).unwrap();
+
+    let data = load_file(&path).unwrap();
+    assert_eq!(data.num_of_records(), 3);
+    assert_eq!(data.variable_data("temps").unwrap().values,
+        NetCDFArray::Float(vec![280.0, 281.5, 282.0, 283.5, 284.0, 285.5]));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn round_trip_cdf2() {
+    round_trip("tests/version2/record1.nc");
+
+    let data = load_file("tests/version2/record1.nc").unwrap();
+    assert_eq!(data.header().version(), NetCDFVersion::CDF02);
+    assert_eq!(data.variable_data("times").unwrap().values, NetCDFArray::Short(vec![1, 2, 3]));
+}
+
+/// vsize and begin of a variable with a single character name in a version 2 header.
+fn variable_layout(buffer: &[u8], name: u8, ndims: usize) -> (u32, u64) {
+    let position = buffer.windows(8).position(|bytes| bytes == [0, 0, 0, 1, name, 0, 0, 0]).unwrap();
+    // Skip name, dimension ids, absent attribute list and nc_type
+    let position = position + 8 + 4 + 4 * ndims + 8 + 4;
+    let vsize = u32::from_be_bytes(buffer[position..position + 4].try_into().unwrap());
+    let begin = u64::from_be_bytes(buffer[position + 4..position + 12].try_into().unwrap());
+    (vsize, begin)
+}
+
+#[test]
+fn switch_to_cdf2() {
+    // Two record variables with 2 GiB per record, no records are written
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", NetCDFDimensionLength::Unlimited)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(1 << 30))
+        .add_variable("a", NetCDFType::NCShort, &["time", "x"])
+        .add_variable("b", NetCDFType::NCShort, &["time", "x"])
+        .build()
+        .unwrap();
+    assert_eq!(netcdf.header().version(), NetCDFVersion::CDF02);
+
+    let buffer = save_to_buffer(&netcdf);
+    assert_eq!(&buffer[..4], b"CDF\x02");
+    let header_size = buffer.len() as u64;
+    assert_eq!(variable_layout(&buffer, b'a', 2), (1 << 31, header_size));
+    assert_eq!(variable_layout(&buffer, b'b', 2), (1 << 31, header_size + (1 << 31)));
+
+    let data = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(data.header().version(), NetCDFVersion::CDF02);
+    assert_eq!(data.num_of_records(), 0);
+
+    // The offset of b still fits into version 1
+    let netcdf = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", NetCDFDimensionLength::Unlimited)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(1 << 29))
+        .add_variable("a", NetCDFType::NCShort, &["time", "x"])
+        .add_variable("b", NetCDFType::NCShort, &["time", "x"])
+        .build()
+        .unwrap();
+    assert_eq!(netcdf.header().version(), NetCDFVersion::CDF01);
+    assert_eq!(&save_to_buffer(&netcdf)[..4], b"CDF\x01");
+}
+
+#[test]
+fn append_record() {
+    let path = std::env::temp_dir().join("netcdfrs_append_record.nc");
+    fs::copy("tests/version1/record1.nc", &path).unwrap();
+
+    let mut appender = open_append(&path).unwrap();
+    appender.append_record(&[NetCDFArray::Short(vec![4]), NetCDFArray::Float(vec![286.0, 287.5])]).unwrap();
+    assert_eq!(appender.header().num_of_records(), 4);
+    assert!(appender.append_record(&[NetCDFArray::Short(vec![5])]).is_err());
+    assert!(appender.append_record(&[NetCDFArray::Short(vec![5]), NetCDFArray::Float(vec![1.0])]).is_err());
+
+    let data = load_file(&path).unwrap();
+    assert_eq!(data.num_of_records(), 4);
+    assert_eq!(data.variable_data("times").unwrap().values, NetCDFArray::Short(vec![1, 2, 3, 4]));
+    assert_eq!(data.variable_data("temps").unwrap().values,
+        NetCDFArray::Float(vec![280.0, 281.5, 282.0, 283.5, 284.0, 285.5, 286.0, 287.5]));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn append_record_single_variable() {
+    let mut buffer = fs::read("tests/version1/record2.nc").unwrap();
+    let mut appender = open_appender(std::io::Cursor::new(&mut buffer)).unwrap();
+    appender.append_record(&[NetCDFArray::Short(vec![4])]).unwrap();
+    appender.append_record(&[NetCDFArray::Short(vec![5])]).unwrap();
+
+    let expected = NetCDFBuilder::new(NetCDFVersion::CDF01)
+        .add_dimension("time", NetCDFDimensionLength::Unlimited)
+        .add_variable("times", NetCDFType::NCShort, &["time"])
+        .set_variable_data("times", NetCDFArray::Short(vec![1, 2, 3, 4, 5]))
+        .build()
+        .unwrap();
+    let mut expected_buffer = Vec::new();
+    save_writer(&mut expected_buffer, &expected).unwrap();
+
+    assert_eq!(buffer, expected_buffer);
+}
+
+#[test]
+fn save_writer_streaming() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+    let options = NetCDFWriteOptions{streaming: true, ..NetCDFWriteOptions::default()};
+    let mut buffer = Vec::new();
+    save_writer_with_options(&mut buffer, &data, &options).unwrap();
+
+    assert_eq!(buffer, fs::read("tests/version1/record1_streaming.nc").unwrap());
+}
+
+#[test]
+fn append_record_streaming() {
+    let mut buffer = fs::read("tests/version1/record1_streaming.nc").unwrap();
+    let mut appender = open_appender(std::io::Cursor::new(&mut buffer)).unwrap();
+    assert_eq!(appender.header().num_of_records(), 3);
+    appender.append_record(&[NetCDFArray::Short(vec![4]), NetCDFArray::Float(vec![286.0, 287.5])]).unwrap();
+    assert_eq!(appender.header().num_of_records(), 4);
+
+    assert_eq!(&buffer[4..8], &[0xff, 0xff, 0xff, 0xff]);
+
+    let data = load_reader(&mut &buffer[..]).unwrap();
+    assert_eq!(data.num_of_records(), 4);
+    assert_eq!(data.variable_data("times").unwrap().values, NetCDFArray::Short(vec![1, 2, 3, 4]));
+}
+
+#[test]
+fn save_writer_free_space() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+    let options = NetCDFWriteOptions{h_minfree: 256, v_align: 512, r_align: 1024, ..NetCDFWriteOptions::default()};
+    let mut buffer = Vec::new();
+    save_writer_with_options(&mut buffer, &data, &options).unwrap();
+
+    // lat at 512, the records start at 1024 and have a size of 12 bytes
+    assert_eq!(buffer.len(), 1024 + (3 * 12));
+    assert_eq!(&buffer[512..520], &[0x41, 0x28, 0, 0, 0x41, 0xa4, 0, 0]);
+
+    let result = load_reader(&mut &buffer[..]).unwrap();
+    for name in ["lat", "times", "temps"].iter() {
+        assert_eq!(result.variable_data(name).unwrap(), data.variable_data(name).unwrap());
+    }
+}
+
+#[test]
+fn round_trip_cdf5() {
+    round_trip("tests/version5/cdf5.nc");
+}
+
+fn save_to_buffer(netcdf: &NetCDF) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    save_writer(&mut buffer, netcdf).unwrap();
+    buffer
+}
+
+#[test]
+fn convert_record() {
+    let data = load_file("tests/version1/record1.nc").unwrap();
+
+    let data = convert_version(data, NetCDFVersion::CDF05).unwrap();
+    assert_eq!(data.header().version(), NetCDFVersion::CDF05);
+    let buffer = save_to_buffer(&data);
+    assert_eq!(&buffer[..4], b"CDF\x05");
+    let data = load_reader(&mut std::io::Cursor::new(buffer)).unwrap();
+    assert_eq!(data.variable_data("temps").unwrap().values,
+        NetCDFArray::Float(vec![280.0, 281.5, 282.0, 283.5, 284.0, 285.5]));
+
+    let data = convert_version(data, NetCDFVersion::CDF02).unwrap();
+    assert_eq!(save_to_buffer(&data), fs::read("tests/version2/record1.nc").unwrap());
+
+    let data = convert_version(data, NetCDFVersion::CDF01).unwrap();
+    assert_eq!(save_to_buffer(&data), fs::read("tests/version1/record1.nc").unwrap());
+}
+
+#[test]
+fn convert_file_cdf5() {
+    let path = std::env::temp_dir().join("netcdfrs_convert_file_cdf5.nc");
+    convert_file("tests/version5/cdf5.nc", &path, NetCDFVersion::CDF05).unwrap();
+    assert_eq!(fs::read(&path).unwrap(), fs::read("tests/version5/cdf5.nc").unwrap());
+    fs::remove_file(&path).unwrap();
+
+    match convert_file("tests/version5/cdf5.nc", &path, NetCDFVersion::CDF02) {
+        Err(NetCDFError::InvalidConversion(message)) =>
+            assert_eq!(message, "variable 'ub' has type ubyte, which needs version 5 (CDF05), got CDF02"),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    assert!(matches!(convert_file("tests/version5/cdf5.nc", &path, NetCDFVersion::CDF01),
+        Err(NetCDFError::InvalidConversion(_))));
+    assert!(matches!(convert_file("tests/version5/cdf5.nc", &path, NetCDFVersion::HDF5),
+        Err(NetCDFError::InvalidConversion(_))));
+    assert!(!path.exists());
+}
+
+/// Without records the size of the record variables does not need any memory.
+fn large_record_variables(version: NetCDFVersion, nc_type: NetCDFType, length: u64) -> NetCDF {
+    NetCDFBuilder::new(version)
+        .add_dimension("time", NetCDFDimensionLength::Unlimited)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(length))
+        .add_variable("a", nc_type, &["time", "x"])
+        .add_variable("b", nc_type, &["time", "x"])
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn convert_large_variables() {
+    let data = large_record_variables(NetCDFVersion::CDF05, NetCDFType::NCInt, 1 << 30);
+    let error = convert_version(data, NetCDFVersion::CDF02).err().unwrap();
+    assert!(error.to_string().starts_with("Invalid conversion: variable 'a' has 4294967296 bytes"), "{}", error);
+
+    // 2 GiB - 4 bytes fit into version 1, but then the offset of b does not
+    let data = large_record_variables(NetCDFVersion::CDF05, NetCDFType::NCShort, (1 << 30) - 2);
+    let data = convert_version(data, NetCDFVersion::CDF02).unwrap();
+    assert_eq!(data.header().version(), NetCDFVersion::CDF02);
+    let error = convert_version(data, NetCDFVersion::CDF01).err().unwrap();
+    assert!(error.to_string().contains("needs offsets larger than"), "{}", error);
+
+    let data = NetCDFBuilder::new(NetCDFVersion::CDF05)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(1 << 32))
+        .build()
+        .unwrap();
+    let error = convert_version(data, NetCDFVersion::CDF02).err().unwrap();
+    assert!(error.to_string().contains("dimension 'x' does not fit into 32 bits"), "{}", error);
+}
+
+#[test]
+fn save_large_variables() {
+    // Only the last record variable can be larger than 4 GiB in version 2
+    let result = NetCDFBuilder::new(NetCDFVersion::CDF02)
+        .add_dimension("time", NetCDFDimensionLength::Unlimited)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(1 << 30))
+        .add_variable("a", NetCDFType::NCInt, &["time", "x"])
+        .add_variable("b", NetCDFType::NCInt, &["time", "x"])
+        .build();
+    assert!(matches!(result, Err(NetCDFError::InvalidData(_))));
+
+    let data = NetCDFBuilder::new(NetCDFVersion::CDF02)
+        .add_dimension("time", NetCDFDimensionLength::Unlimited)
+        .add_dimension("x", NetCDFDimensionLength::Fixed(1 << 30))
+        .add_variable("a", NetCDFType::NCShort, &["time"])
+        .add_variable("b", NetCDFType::NCInt, &["time", "x"])
+        .build()
+        .unwrap();
+    let mut buffer = save_to_buffer(&data);
+
+    // Two records in a sparse file, a is the first variable in each record. The vsize
+    // of b is stored as 2^32 - 1, the size of a record must come from the dimensions.
+    let record_begin = buffer.len() as u64;
+    let record_size = 4 + (1 << 32);
+    buffer[4..8].copy_from_slice(&2u32.to_be_bytes());
+    let path = std::env::temp_dir().join("netcdfrs_save_large_variables.nc");
+    let mut file = fs::File::create(&path).unwrap();
+    file.write_all(&buffer).unwrap();
+    file.set_len(record_begin + (2 * record_size)).unwrap();
+    file.seek(SeekFrom::Start(record_begin + record_size)).unwrap();
+    file.write_all(&7i16.to_be_bytes()).unwrap();
+    drop(file);
+
+    let mut reader = open_file(&path).unwrap();
+    assert_eq!(reader.read_slice("a", &[1], &[1], None).unwrap().values, NetCDFArray::Short(vec![7]));
+    drop(reader);
+    fs::remove_file(&path).unwrap();
+}